@@ -1,374 +1,798 @@
-use crate::parser::{Ast, BinOpType, Node, StringLiteralEntry, Ty};
+mod aarch64;
+mod backend;
+mod target;
+mod x86_64;
 
-const FRAME_POINTER_REGISTER: &str = "x29";
-const LINK_REGISTER: &str = "x30";
-const STACK_ALIGNMENT: i32 = 16;
+use std::io::Write;
+
+use crate::{
+    error_report::Diagnostic,
+    parser::{Ast, BinOpType, Node, StringLiteralEntry, Ty},
+};
+
+pub use self::{aarch64::Aarch64Backend, backend::Backend, target::Target, x86_64::X86_64Backend};
+
+// caseがこの数以上あり、値の範囲がcaseの数のこの倍以内に収まれば表を使って飛ぶ
+const JUMP_TABLE_MIN_CASES: usize = 4;
+const JUMP_TABLE_MAX_SPARSENESS: i64 = 3;
+
+// ターゲットに対応するBackendを実行時に選ぶ
+pub fn backend_for(target: Target) -> Box<dyn Backend> {
+    match target {
+        Target::Aarch64AppleDarwin | Target::Aarch64LinuxGnu => {
+            Box::new(Aarch64Backend::new(target))
+        }
+        Target::X86_64LinuxGnu => Box::new(X86_64Backend::new(target)),
+    }
+}
 
 pub struct CodeGenerator {
     program: Vec<Node>,
     string_literal_entries: Vec<StringLiteralEntry>,
+    backend: Box<dyn Backend>,
+    // プログラム中でユニークなラベルを生成するため
+    label_index: i32,
+    // 囲んでいるループやswitchのbreak, continueの飛び先。一番内側のものが末尾に来る
+    break_labels: Vec<String>,
+    continue_labels: Vec<String>,
+    // 囲んでいるswitchのラベルの番号。caseのラベルを作るのに使う
+    switch_indices: Vec<i32>,
 }
 
 impl CodeGenerator {
-    pub fn new(program: Vec<Node>, string_literal_entries: Vec<StringLiteralEntry>) -> Self {
+    pub fn new(
+        program: Vec<Node>,
+        string_literal_entries: Vec<StringLiteralEntry>,
+        backend: Box<dyn Backend>,
+    ) -> Self {
         Self {
             program,
             string_literal_entries,
+            backend,
+            label_index: 0,
+            break_labels: vec![],
+            continue_labels: vec![],
+            switch_indices: vec![],
         }
     }
 
-    pub fn generate(&self) {
-        println!("\t.section	__TEXT,__text,regular,pure_instructions");
-        // プログラム中でユニークなラベルを生成するため
-        let mut label_index = 0;
-        for stmt in self.program.iter() {
+    pub fn generate(mut self, out: &mut dyn Write) -> Result<(), Diagnostic> {
+        self.backend.text_section(out)?;
+        let program = std::mem::take(&mut self.program);
+        for stmt in program.iter() {
             match stmt {
                 Node {
                     ast: Ast::Fundef { name, .. },
                     ..
                 } => {
-                    self.gen(stmt, &mut label_index, Some(name));
+                    self.gen(out, stmt, Some(name))?;
                 }
                 Node {
                     ast: Ast::GlobalVarDef(name, ty),
                     ..
                 } => {
-                    println!(".comm _{},{}", name, ty.size());
+                    self.backend.global_var(out, name, ty)?;
                 }
                 _ => {
-                    panic!("Unsupported toplevel node: {:?}", stmt);
+                    return Err(Diagnostic::error(format!(
+                        "Unsupported toplevel node: {:?}",
+                        stmt.ast
+                    )));
                 }
             }
         }
 
-        if self.string_literal_entries.len() > 0 {
-            println!("\t.section	__TEXT,__cstring,cstring_literals");
-            for st_ent in self.string_literal_entries.iter() {
-                println!("{}:", st_ent.label);
-                println!("\t.asciz \"{}\"", st_ent.contents);
-            }
-        }
+        self.backend
+            .string_literals(out, &self.string_literal_entries)?;
+        self.backend.finish(out)?;
+
+        Ok(())
     }
 
-    fn gen(&self, node: &Node, label_index: &mut i32, current_fn_name: Option<&str>) {
-        self.generate_comment(&format!("Compiling: {:?}", node));
+    fn gen(
+        &mut self,
+        out: &mut dyn Write,
+        node: &Node,
+        current_fn_name: Option<&str>,
+    ) -> Result<(), Diagnostic> {
+        self.backend
+            .comment(out, &format!("Compiling: {:?}", node))?;
         match &node.ast {
             Ast::Num(n) => {
-                self.generate_comment(&format!("num: {}", n));
-                println!("\tmov x2, #{}", n);
-                self.generate_push_register_to_stack("x2");
+                self.backend.push_num(out, *n)?;
             }
             Ast::LocalVarDef(_, _) => {}
             Ast::GlobalVarDef(..) => {}
-            Ast::LocalVar { name, offset, .. } => {
-                self.generate_comment(&format!("local var {} at {}", name, offset));
-
-                self.generate_comment("\t local var push address to stack");
-                self.generate_var(node);
-
-                self.generate_comment("\t local var pop address from stack");
-                self.generate_pop_register_from_stack("x0");
-
-                self.generate_comment("\t local var read address content to register");
-                self.load(&node.ty);
-
-                self.generate_comment("\t local var push value to stack");
-                self.generate_push_register_to_stack("x0");
-            }
-            Ast::GlobalVar { name } => {
-                self.generate_comment(&format!("global var {}", name));
-
-                self.generate_comment("\t global var push address to stack");
-                self.generate_var(node);
-
-                self.generate_comment("\t global var pop address from stack");
-                self.generate_pop_register_from_stack("x0");
-
-                self.generate_comment("\t global var read address content to register");
-                self.load(&node.ty);
-
-                self.generate_comment("\t global var push value to stack");
-                self.generate_push_register_to_stack("x0");
-            }
-            Ast::StringLiteral { label } => {
-                self.generate_comment(&format!("string literal with label: {}", label));
-
-                self.generate_comment("\t string literal var push address to stack");
-                println!("\tadrp x0, {}@PAGE", label);
-                println!("\tadd  x0, x0, {}@PAGEOFF", label);
-
-                self.generate_comment("\t string literal read address content to register");
-                self.load(&node.ty);
-
-                self.generate_comment("\t global var push value to stack");
-                self.generate_push_register_to_stack("x0");
+            Ast::LocalVar { .. }
+            | Ast::GlobalVar { .. }
+            | Ast::StringLiteral { .. }
+            | Ast::Member { .. } => {
+                self.generate_var(out, node, current_fn_name)?;
+                self.load(out, &node.ty)?;
             }
             Ast::Addr(base_node) => {
-                self.generate_var(base_node);
+                self.generate_var(out, base_node, current_fn_name)?;
             }
             Ast::Deref(base_node) => {
-                self.gen(base_node, label_index, current_fn_name);
-                self.generate_pop_register_from_stack("x0");
-                self.load(&node.ty);
-                self.generate_push_register_to_stack("x0");
+                self.gen(out, base_node, current_fn_name)?;
+                self.load(out, &node.ty)?;
+            }
+            Ast::Cast(base_node) => {
+                self.gen(out, base_node, current_fn_name)?;
+                self.backend.cast(out, node.ty.as_ref().unwrap())?;
+            }
+            Ast::BitNot(operand) => {
+                self.gen(out, operand, current_fn_name)?;
+                self.backend.bit_not(out)?;
             }
             Ast::Assign(lhs, rhs) => {
-                self.generate_comment("assign");
-
-                self.generate_comment("\tassign push lhs(address)");
-                if let Node {
-                    ast: Ast::Deref(derefed_lhs),
-                    ..
-                } = &**lhs
-                {
-                    self.gen(derefed_lhs, label_index, current_fn_name);
-                } else {
-                    self.generate_var(lhs.as_ref());
-                }
-
-                self.generate_comment("\tassign push rhs(value)");
-                self.gen(rhs.as_ref(), label_index, current_fn_name);
+                self.backend.comment(out, "assign push lhs(address)")?;
+                self.generate_var(out, lhs.as_ref(), current_fn_name)?;
 
-                self.generate_comment("\tassign pop value and address");
-                self.generate_pop_register_from_stack("x1");
-                self.generate_pop_register_from_stack("x0");
+                self.backend.comment(out, "assign push rhs(value)")?;
+                self.gen(out, rhs.as_ref(), current_fn_name)?;
 
-                self.generate_comment("\tassign store values to address");
-                self.store(&rhs.as_ref().ty);
-
-                // Cでは代入式は代入された値を返す
-                self.generate_comment("\tassign push assigned value to stack");
-                self.generate_push_register_to_stack("x1");
+                self.backend.store(out, node.ty.as_ref().unwrap())?;
+            }
+            Ast::CompoundAssign(op, lhs, rhs) => {
+                let ty = node.ty.as_ref().unwrap();
+                self.backend
+                    .comment(out, "compound assign push lhs(address)")?;
+                self.generate_var(out, lhs.as_ref(), current_fn_name)?;
+                // アドレスを残したまま今の値を読む
+                self.backend.dup(out)?;
+                self.backend.load(out, ty)?;
+                self.gen(out, rhs.as_ref(), current_fn_name)?;
+                self.backend.binop(out, op)?;
+                self.backend.cast(out, ty)?;
+                self.backend.store(out, ty)?;
+            }
+            Ast::Conditional(condition, then_value, else_value) => {
+                self.gen(out, condition.as_ref(), current_fn_name)?;
+
+                let idx = self.increment_label_index();
+                self.backend
+                    .branch_if_zero(out, &format!(".Lelse{}", idx))?;
+                self.gen(out, then_value.as_ref(), current_fn_name)?;
+                self.backend.jump(out, &format!(".Lend{}", idx))?;
+                self.backend.label(out, &format!(".Lelse{}", idx))?;
+                self.gen(out, else_value.as_ref(), current_fn_name)?;
+                self.backend.label(out, &format!(".Lend{}", idx))?;
+                self.backend.join_branch_values();
+            }
+            Ast::Comma(lhs, rhs) => {
+                self.gen_stmt(out, lhs.as_ref(), current_fn_name)?;
+                self.gen(out, rhs.as_ref(), current_fn_name)?;
+            }
+            Ast::LogicalAnd(lhs, rhs) => {
+                let idx = self.increment_label_index();
+                self.gen(out, lhs.as_ref(), current_fn_name)?;
+                self.backend
+                    .branch_if_zero(out, &format!(".Lfalse{}", idx))?;
+                self.gen(out, rhs.as_ref(), current_fn_name)?;
+                self.backend
+                    .branch_if_zero(out, &format!(".Lfalse{}", idx))?;
+                self.backend.push_num(out, 1)?;
+                self.backend.jump(out, &format!(".Lend{}", idx))?;
+                self.backend.label(out, &format!(".Lfalse{}", idx))?;
+                self.backend.push_num(out, 0)?;
+                self.backend.label(out, &format!(".Lend{}", idx))?;
+                self.backend.join_branch_values();
+            }
+            Ast::LogicalOr(lhs, rhs) => {
+                let idx = self.increment_label_index();
+                self.gen(out, lhs.as_ref(), current_fn_name)?;
+                self.backend
+                    .branch_if_not_zero(out, &format!(".Ltrue{}", idx))?;
+                self.gen(out, rhs.as_ref(), current_fn_name)?;
+                self.backend
+                    .branch_if_not_zero(out, &format!(".Ltrue{}", idx))?;
+                self.backend.push_num(out, 0)?;
+                self.backend.jump(out, &format!(".Lend{}", idx))?;
+                self.backend.label(out, &format!(".Ltrue{}", idx))?;
+                self.backend.push_num(out, 1)?;
+                self.backend.label(out, &format!(".Lend{}", idx))?;
+                self.backend.join_branch_values();
             }
             Ast::If(condition, then_body, else_body) => {
-                self.generate_comment("if");
-                self.generate_comment("\tif condition");
-                self.gen(condition.as_ref(), label_index, current_fn_name);
-                self.generate_pop_register_from_stack("x0");
-                println!("\tcmp x0, #0");
+                self.gen(out, condition.as_ref(), current_fn_name)?;
 
-                let idx = self.increment_label_index(label_index);
+                let idx = self.increment_label_index();
                 if let Some(else_body) = &else_body {
-                    println!("\tb.eq .Lelse{}", idx);
-                    self.generate_comment("\tif then-body");
-                    self.gen(then_body.as_ref(), label_index, current_fn_name);
-                    println!("\tb .Lend{}", idx);
-                    println!(".Lelse{}:", idx);
-                    self.generate_comment("\tif then-else-body");
-                    self.gen(else_body, label_index, current_fn_name);
+                    self.backend
+                        .branch_if_zero(out, &format!(".Lelse{}", idx))?;
+                    self.gen_stmt(out, then_body.as_ref(), current_fn_name)?;
+                    self.backend.jump(out, &format!(".Lend{}", idx))?;
+                    self.backend.label(out, &format!(".Lelse{}", idx))?;
+                    self.gen_stmt(out, else_body, current_fn_name)?;
                 } else {
-                    println!("\tb.eq .Lend{}", idx);
-                    self.gen(then_body.as_ref(), label_index, current_fn_name);
+                    self.backend.branch_if_zero(out, &format!(".Lend{}", idx))?;
+                    self.gen_stmt(out, then_body.as_ref(), current_fn_name)?;
                 }
 
-                println!(".Lend{}:", idx);
+                self.backend.label(out, &format!(".Lend{}", idx))?;
             }
             Ast::While(condition, body) => {
-                let idx = self.increment_label_index(label_index);
-                println!(".Lbegin{}:", idx);
-                self.gen(condition.as_ref(), label_index, current_fn_name);
-                self.generate_pop_register_from_stack("x0");
-                println!("\tcmp x0, #0");
-                println!("\tb.eq .Lend{}", idx);
-                self.gen(body.as_ref(), label_index, current_fn_name);
-                println!("\tb .Lbegin{}", idx);
-                println!(".Lend{}:", idx);
+                let idx = self.increment_label_index();
+                self.backend.label(out, &format!(".Lbegin{}", idx))?;
+                self.gen(out, condition.as_ref(), current_fn_name)?;
+                self.backend.branch_if_zero(out, &format!(".Lend{}", idx))?;
+                self.gen_loop_body(
+                    out,
+                    body.as_ref(),
+                    format!(".Lend{}", idx),
+                    format!(".Lbegin{}", idx),
+                    current_fn_name,
+                )?;
+                self.backend.jump(out, &format!(".Lbegin{}", idx))?;
+                self.backend.label(out, &format!(".Lend{}", idx))?;
+            }
+            Ast::DoWhile(body, condition) => {
+                let idx = self.increment_label_index();
+                self.backend.label(out, &format!(".Lbegin{}", idx))?;
+                // continueは条件の評価へ飛ぶ
+                self.gen_loop_body(
+                    out,
+                    body.as_ref(),
+                    format!(".Lend{}", idx),
+                    format!(".Lcontinue{}", idx),
+                    current_fn_name,
+                )?;
+                self.backend.label(out, &format!(".Lcontinue{}", idx))?;
+                self.gen(out, condition.as_ref(), current_fn_name)?;
+                self.backend
+                    .branch_if_not_zero(out, &format!(".Lbegin{}", idx))?;
+                self.backend.label(out, &format!(".Lend{}", idx))?;
             }
             Ast::For(init, check, update, body) => {
-                let idx = self.increment_label_index(label_index);
+                let idx = self.increment_label_index();
                 if let Some(init) = init {
-                    self.gen(init.as_ref(), label_index, current_fn_name);
+                    self.gen_stmt(out, init.as_ref(), current_fn_name)?;
                 }
-                println!(".Lbegin{}:", idx);
+                self.backend.label(out, &format!(".Lbegin{}", idx))?;
+                // checkがない場合は常にtrueとして扱う
                 if let Some(check) = check {
-                    self.gen(check.as_ref(), label_index, current_fn_name);
-                } else {
-                    // checkがない場合は常にtrueにする
-                    self.gen(
-                        &Node::new(Ast::Num(1), Some(Ty::Int)),
-                        label_index,
-                        current_fn_name,
-                    );
+                    self.gen(out, check.as_ref(), current_fn_name)?;
+                    self.backend.branch_if_zero(out, &format!(".Lend{}", idx))?;
                 }
-                self.generate_pop_register_from_stack("x0");
-                println!("\tcmp x0, #0");
-                println!("\tb.eq .Lend{}", idx);
-                self.gen(body.as_ref(), label_index, current_fn_name);
+                // continueは更新の式へ飛ぶ
+                self.gen_loop_body(
+                    out,
+                    body.as_ref(),
+                    format!(".Lend{}", idx),
+                    format!(".Lcontinue{}", idx),
+                    current_fn_name,
+                )?;
+                self.backend.label(out, &format!(".Lcontinue{}", idx))?;
                 if let Some(update) = update {
-                    self.gen(update.as_ref(), label_index, current_fn_name);
+                    self.gen_stmt(out, update.as_ref(), current_fn_name)?;
                 }
-                println!("\tb .Lbegin{}", idx);
-                println!(".Lend{}:", idx);
+                self.backend.jump(out, &format!(".Lbegin{}", idx))?;
+                self.backend.label(out, &format!(".Lend{}", idx))?;
+            }
+            Ast::Break => {
+                let label = self.break_labels.last().cloned().ok_or_else(|| {
+                    Diagnostic::error("'break' statement not in loop or switch statement")
+                })?;
+                self.backend.jump(out, &label)?;
+            }
+            Ast::Continue => {
+                let label = self.continue_labels.last().cloned().ok_or_else(|| {
+                    Diagnostic::error("'continue' statement not in loop statement")
+                })?;
+                self.backend.jump(out, &label)?;
+            }
+            Ast::Switch {
+                condition,
+                body,
+                case_values,
+                has_default,
+            } => {
+                self.gen(out, condition.as_ref(), current_fn_name)?;
+
+                let idx = self.increment_label_index();
+                let end_label = format!(".Lend{}", idx);
+                let default_label = if *has_default {
+                    format!(".Ldefault{}", idx)
+                } else {
+                    end_label.clone()
+                };
+                self.gen_switch_dispatch(out, idx, case_values, &default_label)?;
+
+                // switchの中のbreakはswitchを抜けるが、continueは外側のループに進む
+                self.break_labels.push(end_label.clone());
+                self.switch_indices.push(idx);
+                let result = self.gen_stmt(out, body.as_ref(), current_fn_name);
+                self.switch_indices.pop();
+                self.break_labels.pop();
+                result?;
+
+                self.backend.label(out, &end_label)?;
+            }
+            Ast::Case { index, body } => {
+                let switch_idx =
+                    self.current_switch_index("'case' statement not in switch statement")?;
+                self.backend
+                    .label(out, &Self::case_label(switch_idx, *index))?;
+                self.gen_stmt(out, body.as_ref(), current_fn_name)?;
+            }
+            Ast::Default(body) => {
+                let switch_idx =
+                    self.current_switch_index("'default' statement not in switch statement")?;
+                self.backend
+                    .label(out, &format!(".Ldefault{}", switch_idx))?;
+                self.gen_stmt(out, body.as_ref(), current_fn_name)?;
             }
             Ast::Block(stmts) => {
                 for s in stmts {
-                    self.gen(s, label_index, current_fn_name);
-                    self.generate_pop_register_from_stack("x0");
+                    self.gen_stmt(out, s, current_fn_name)?;
                 }
             }
-            Ast::Funcall(name, args) => {
+            Ast::Funcall(name, args, fixed_arg_count) => {
                 for a in args {
-                    self.gen(a, label_index, current_fn_name);
+                    self.gen(out, a, current_fn_name)?;
                 }
-                for i in (0..args.len()).rev() {
-                    self.generate_pop_register_from_stack(&format!("x{}", i));
+                let arg_tys = args
+                    .iter()
+                    .map(|arg| arg.ty.clone().unwrap())
+                    .collect::<Vec<_>>();
+                self.backend.call(out, name, &arg_tys, *fixed_arg_count)?;
+                // 戻り値のレジスタの上位ビットは不定なので、宣言された型に合わせて拡張する
+                if node.ty != Some(Ty::Void) {
+                    self.backend.cast(out, node.ty.as_ref().unwrap())?;
                 }
-                println!("\tbl _{}", name);
-                // 関数の戻り値はx0に入っている
-                self.generate_push_register_to_stack("x0");
             }
             Ast::Return(value) => {
-                self.generate_comment("return");
-                self.gen(value.as_ref(), label_index, current_fn_name);
-                self.generate_pop_register_from_stack("x0");
-                println!("\tb .L.return_{}", current_fn_name.unwrap());
+                self.backend.comment(out, "return")?;
+                if let Some(value) = value {
+                    self.gen(out, value.as_ref(), current_fn_name)?;
+                    self.backend.return_value(out)?;
+                }
+                self.backend
+                    .jump(out, &format!(".L.return_{}", current_fn_name.unwrap()))?;
             }
             Ast::Fundef {
                 name,
                 args,
                 body,
                 stack_size,
+                va_area,
             } => {
-                println!("\t.globl _{}", name);
-                println!("\t.p2align 2");
-                println!("_{}:", name);
-                self.generate_comment("Store FP & LR to stack");
-                println!(
-                    "\tstp {}, {}, [sp, #-16]!",
-                    FRAME_POINTER_REGISTER, LINK_REGISTER
-                );
-                self.generate_comment("Update FP");
-                println!("\tmov {}, sp", FRAME_POINTER_REGISTER);
-                self.generate_comment("Allocate stack space for local variables & arguments");
-                println!("\tsub sp, sp, #{}", stack_size);
-                self.generate_comment("Copy arguments into stack");
-                for arg in args.iter().enumerate() {
-                    if let Ast::LocalVar { offset, .. } = arg.1.ast {
-                        println!(
-                            "\tstur x{}, [{}, #-{}]",
-                            arg.0, FRAME_POINTER_REGISTER, offset,
-                        );
+                self.backend.prologue(out, name, *stack_size)?;
+                self.backend.comment(out, "Copy arguments into stack")?;
+                let arg_tys = args
+                    .iter()
+                    .map(|arg| arg.ty.clone().unwrap())
+                    .collect::<Vec<_>>();
+                for (i, arg) in args.iter().enumerate() {
+                    if let Ast::LocalVar { offset, .. } = arg.ast {
+                        self.backend.store_arg(out, i, offset, &arg_tys)?;
                     } else {
-                        panic!("unexpected function arg ast: {:?}", arg.1.ast);
+                        return Err(Diagnostic::error(format!(
+                            "unexpected function arg ast: {:?}",
+                            arg.ast
+                        )));
                     }
                 }
+                if let Some(va_area) = va_area {
+                    self.backend
+                        .comment(out, "Save argument registers for va_arg")?;
+                    self.backend.save_va_registers(out, *va_area)?;
+                }
                 for s in body {
-                    self.gen(s, label_index, Some(name));
+                    self.gen_stmt(out, s, Some(name))?;
                 }
-                println!(".L.return_{}:", name);
-                self.generate_comment("Restore FP & LR from stack");
-                println!("\tmov sp, {}", FRAME_POINTER_REGISTER);
-                println!(
-                    "\tldp {}, {}, [sp], #16",
-                    FRAME_POINTER_REGISTER, LINK_REGISTER
-                );
-                println!("\tret")
+                self.backend.label(out, &format!(".L.return_{}", name))?;
+                self.backend.epilogue(out)?;
+            }
+            Ast::VaStart {
+                ap,
+                arg_tys,
+                va_area,
+            } => {
+                self.gen(out, ap, current_fn_name)?;
+                self.backend.va_start(out, arg_tys, *va_area)?;
+                // va_startはvoidだが、式として値を1つ積んでおく
+                self.backend.push_num(out, 0)?;
+            }
+            Ast::VaArg(ap) => {
+                self.gen(out, ap, current_fn_name)?;
+                self.backend.va_arg(out)?;
+                self.load(out, &node.ty)?;
             }
             Ast::BinOp(op, lhs, rhs) => {
-                self.gen(lhs.as_ref(), label_index, current_fn_name);
-                self.gen(rhs.as_ref(), label_index, current_fn_name);
-                self.generate_pop_register_from_stack("x1");
-                self.generate_pop_register_from_stack("x0");
-
-                match *op {
-                    BinOpType::Add => println!("\tadd x0, x0, x1"),
-                    BinOpType::Sub => println!("\tsub x0, x0, x1"),
-                    BinOpType::Mul => println!("\tmul x0, x0, x1"),
-                    BinOpType::Div => println!("\tsdiv x0, x0, x1"),
-                    BinOpType::Equal => {
-                        println!("\tcmp x0, x1");
-                        println!("\tcset x0, EQ");
-                    }
-                    BinOpType::NotEqual => {
-                        println!("\tcmp x0, x1");
-                        println!("\tcset x0, NE");
-                    }
-                    BinOpType::LessThan => {
-                        println!("\tcmp x0, x1");
-                        println!("\tcset x0, LT");
-                    }
-                    BinOpType::LessThanOrEqual => {
-                        println!("\tcmp x0, x1");
-                        println!("\tcset x0, LE");
-                    }
-                }
-                self.generate_push_register_to_stack("x0");
+                self.gen(out, lhs.as_ref(), current_fn_name)?;
+                self.gen(out, rhs.as_ref(), current_fn_name)?;
+                self.backend.binop(out, op)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // 文として評価する。式文の場合はスタックに積まれた値を捨てる
+    fn gen_stmt(
+        &mut self,
+        out: &mut dyn Write,
+        node: &Node,
+        current_fn_name: Option<&str>,
+    ) -> Result<(), Diagnostic> {
+        self.gen(out, node, current_fn_name)?;
+        if Self::pushes_value(node) {
+            self.backend.pop_discard(out)?;
+        }
+
+        Ok(())
+    }
+
+    fn gen_loop_body(
+        &mut self,
+        out: &mut dyn Write,
+        body: &Node,
+        break_label: String,
+        continue_label: String,
+        current_fn_name: Option<&str>,
+    ) -> Result<(), Diagnostic> {
+        self.break_labels.push(break_label);
+        self.continue_labels.push(continue_label);
+        let result = self.gen_stmt(out, body, current_fn_name);
+        self.break_labels.pop();
+        self.continue_labels.pop();
+
+        result
+    }
+
+    // caseの値が密に並んでいれば表を引いて飛び、そうでなければ順に比べる
+    fn gen_switch_dispatch(
+        &mut self,
+        out: &mut dyn Write,
+        idx: i32,
+        case_values: &[i32],
+        default_label: &str,
+    ) -> Result<(), Diagnostic> {
+        let (min_value, max_value) = match (case_values.iter().min(), case_values.iter().max()) {
+            (Some(min_value), Some(max_value)) => (*min_value, *max_value),
+            _ => {
+                self.backend.compare_chain(out, &[], default_label)?;
+                return Ok(());
+            }
+        };
+        let range = max_value as i64 - min_value as i64 + 1;
+        if case_values.len() >= JUMP_TABLE_MIN_CASES
+            && range <= case_values.len() as i64 * JUMP_TABLE_MAX_SPARSENESS
+        {
+            let mut labels = vec![default_label.to_owned(); range as usize];
+            for (index, value) in case_values.iter().enumerate() {
+                labels[(*value as i64 - min_value as i64) as usize] = Self::case_label(idx, index);
             }
+            self.backend.jump_table(
+                out,
+                min_value,
+                &labels,
+                default_label,
+                &format!(".Ltable{}", idx),
+            )?;
+        } else {
+            let cases = case_values
+                .iter()
+                .enumerate()
+                .map(|(index, value)| (*value, Self::case_label(idx, index)))
+                .collect::<Vec<_>>();
+            self.backend.compare_chain(out, &cases, default_label)?;
         }
+
+        Ok(())
+    }
+
+    fn case_label(switch_idx: i32, index: usize) -> String {
+        format!(".Lcase{}_{}", switch_idx, index)
     }
 
-    fn increment_label_index(&self, label_index: &mut i32) -> i32 {
-        let idx = *label_index;
-        *label_index += 1;
+    fn pushes_value(node: &Node) -> bool {
+        !matches!(
+            node.ast,
+            Ast::LocalVarDef(..)
+                | Ast::GlobalVarDef(..)
+                | Ast::If(..)
+                | Ast::While(..)
+                | Ast::DoWhile(..)
+                | Ast::For(..)
+                | Ast::Break
+                | Ast::Continue
+                | Ast::Switch { .. }
+                | Ast::Case { .. }
+                | Ast::Default(..)
+                | Ast::Block(..)
+                | Ast::Return(..)
+                | Ast::Fundef { .. }
+        )
+    }
+
+    // caseとdefaultが属するswitchの番号。switchの外ならmessageのエラーにする
+    fn current_switch_index(&self, message: &str) -> Result<i32, Diagnostic> {
+        self.switch_indices
+            .last()
+            .copied()
+            .ok_or_else(|| Diagnostic::error(message))
+    }
+
+    fn increment_label_index(&mut self) -> i32 {
+        let idx = self.label_index;
+        self.label_index += 1;
 
         idx
     }
 
-    fn generate_var(&self, node: &Node) {
+    // 左辺値のアドレスをスタックに積む
+    fn generate_var(
+        &mut self,
+        out: &mut dyn Write,
+        node: &Node,
+        current_fn_name: Option<&str>,
+    ) -> Result<(), Diagnostic> {
         match &node.ast {
             Ast::LocalVar { offset, .. } => {
-                println!("\tmov x0, {}", FRAME_POINTER_REGISTER);
-                println!("\tsub x0, x0, #{}", offset);
-                self.generate_push_register_to_stack("x0");
+                self.backend.push_local_address(out, *offset)?;
             }
             Ast::GlobalVar { name } => {
-                println!("\tadrp x0, _{}@GOTPAGE", name);
-                println!("\tldr x0, [x0, _{}@GOTPAGEOFF]", name);
-                self.generate_push_register_to_stack("x0");
+                self.backend.push_global_address(out, name)?;
+            }
+            Ast::StringLiteral { label } => {
+                self.backend.push_string_literal_address(out, label)?;
+            }
+            Ast::Deref(derefed) => {
+                self.gen(out, derefed, current_fn_name)?;
+            }
+            Ast::Member { base, offset, .. } => {
+                match &base.ast {
+                    Ast::LocalVar { .. }
+                    | Ast::GlobalVar { .. }
+                    | Ast::Deref(..)
+                    | Ast::Member { .. } => self.generate_var(out, base, current_fn_name)?,
+                    // 代入やカンマ演算子などの結果の構造体は、評価すればそのアドレスが積まれる
+                    _ => self.gen(out, base, current_fn_name)?,
+                }
+                self.backend.push_num(out, *offset)?;
+                self.backend.binop(out, &BinOpType::Add)?;
             }
             _ => {
-                panic!("Node: {:?} is not local var", node);
+                return Err(Diagnostic::error(format!("{:?} is not a lvalue", node.ast)));
             }
         }
+
+        Ok(())
     }
 
-    fn load(&self, ty: &Option<Ty>) {
-        self.generate_comment(&format!("Load {:?} type value from x0", ty));
+    fn load(&mut self, out: &mut dyn Write, ty: &Option<Ty>) -> Result<(), Diagnostic> {
         match *ty {
             Some(Ty::Array(..)) => {
                 // 配列は先頭要素へのポインターとして扱うので、アドレスからロードはしない
-                self.generate_comment("Treat array as pointer");
+                self.backend.comment(out, "Treat array as pointer")?;
             }
-            Some(ref non_array_ty) => match non_array_ty.size() {
-                1 => println!("\tldrsb x0, [x0]"),
-                4 => println!("\tldrsw x0, [x0]"),
-                8 => println!("\tldr x0, [x0]"),
-                _ => panic!("ty: {:?} is not supported", non_array_ty),
-            },
+            Some(Ty::Struct(_) | Ty::Union(_)) => {
+                // 構造体や共用体はレジスタに収まらないので、アドレスのまま扱う
+                self.backend.comment(out, "Treat struct as its address")?;
+            }
+            Some(ref non_array_ty) => self.backend.load(out, non_array_ty)?,
             None => {
-                panic!("ty is None");
+                return Err(Diagnostic::error("Cannot load a value without a type"));
             }
         }
+
+        Ok(())
     }
+}
 
-    fn store(&self, ty: &Option<Ty>) {
-        self.generate_comment(&format!("Store {:?} type value from x1", ty));
-        match *ty {
-            Some(Ty::Array(..)) => {
-                // Store array as pointer to head element
-                println!("\tstr x1, [x0]");
-            }
-            Some(ref non_array_ty) => match non_array_ty.size() {
-                1 => println!("\tstrb w1, [x0]"),
-                4 => println!("\tstr w1, [x0]"),
-                8 => println!("\tstr x1, [x0]"),
-                _ => panic!("ty: {:?} is not supported", non_array_ty),
+#[cfg(test)]
+mod tests {
+    use super::{Aarch64Backend, Backend, CodeGenerator, Target, X86_64Backend};
+    use crate::{
+        parser::{Ast, Node, Parser},
+        tokenizer::Tokenizer,
+    };
+
+    fn generate(program: &str, backend: Box<dyn Backend>) -> String {
+        let token_list = Tokenizer::new(program).tokenize().unwrap();
+        let (program_node, string_literals) = Parser::new(token_list).program().unwrap();
+        let mut out = vec![];
+        CodeGenerator::new(program_node, string_literals, backend)
+            .generate(&mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn stray_break_is_diagnostic() {
+        // パーサーを通らない木でも、ループの外のbreakはパニックせずにエラーにする
+        let program = vec![Node::new(
+            Ast::Fundef {
+                name: "main".to_owned(),
+                args: vec![],
+                body: vec![Node::new(Ast::Break, None)],
+                stack_size: 16,
+                va_area: None,
             },
-            None => {
-                panic!("ty is None");
-            }
-        }
+            None,
+        )];
+        let mut out = vec![];
+        let result = CodeGenerator::new(
+            program,
+            vec![],
+            Box::new(X86_64Backend::new(Target::X86_64LinuxGnu)),
+        )
+        .generate(&mut out);
+        assert_eq!(
+            result.unwrap_err().message,
+            "'break' statement not in loop or switch statement"
+        );
+    }
+
+    #[test]
+    fn generate_darwin_main() {
+        let asm = generate(
+            "int main() { return 42; }",
+            Box::new(Aarch64Backend::new(Target::Aarch64AppleDarwin)),
+        );
+        assert!(asm.contains("\t.globl _main\n"));
+        assert!(asm.contains("\tmov x2, #42\n"));
+        assert!(asm.contains("\tb .L.return_main\n"));
+    }
+
+    #[test]
+    fn generate_linux_global_var() {
+        let asm = generate(
+            "int x; int main() { x = 1; return x; }",
+            Box::new(Aarch64Backend::new(Target::Aarch64LinuxGnu)),
+        );
+        assert!(asm.contains(".comm x,4\n"));
+        assert!(asm.contains("\tadd  x0, x0, :lo12:x\n"));
+        assert!(!asm.contains("_x"));
+    }
+
+    #[test]
+    fn generate_x86_64_funcall() {
+        let asm = generate(
+            "int main() { return add(1, 2); }",
+            Box::new(X86_64Backend::new(Target::X86_64LinuxGnu)),
+        );
+        assert!(asm.contains("\tmov rdi, qword ptr [rsp+8]\n\tmov rsi, qword ptr [rsp+0]\n"));
+        assert!(asm.contains("\tcall add\n\tadd rsp, 16\n"));
+    }
+
+    #[test]
+    fn generate_aarch64_stack_args() {
+        let program = "int f(int a, int b, int c, int d, int e, int f, int g, int h, char i, int j) { return j; } int main() { return f(1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }";
+        let linux = generate(
+            program,
+            Box::new(Aarch64Backend::new(Target::Aarch64LinuxGnu)),
+        );
+        assert!(linux.contains("\tldrb w9, [x29, #16]\n"));
+        assert!(linux.contains("\tldr w9, [x29, #24]\n"));
+        assert!(linux.contains("\tsub sp, sp, #16\n\tldr x9, [sp, #32]\n\tstr x9, [sp, #0]\n\tldr x9, [sp, #16]\n\tstr x9, [sp, #8]\n"));
+        assert!(linux.contains("\tbl f\n\tadd sp, sp, #176\n"));
+        // macOSではスタックの引数を型のサイズで詰めて置く
+        let darwin = generate(
+            program,
+            Box::new(Aarch64Backend::new(Target::Aarch64AppleDarwin)),
+        );
+        assert!(darwin.contains("\tldr w9, [x29, #20]\n"));
+        assert!(darwin.contains("\tstr w9, [sp, #4]\n"));
+    }
+
+    #[test]
+    fn generate_aarch64_large_frame() {
+        let params = (0..70)
+            .map(|i| format!("int a{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let asm = generate(
+            &format!("int f({}) {{ char buf[5000]; return a69; }}", params),
+            Box::new(Aarch64Backend::new(Target::Aarch64LinuxGnu)),
+        );
+        // 即値に収まらない大きさやオフセットは、レジスタに求めてから使う
+        assert!(asm.contains("\tmov x16, #-5296\n\tadd sp, sp, x16\n"));
+        assert!(asm.contains("\tadd x10, x29, #264\n\tldr w9, [x10]\n"));
+        assert!(asm.contains("\tstur w9, [x29, #-256]\n"));
+        assert!(asm.contains("\tsub x10, x29, #260\n\tstr w9, [x10]\n"));
+    }
+
+    #[test]
+    fn generate_aarch64_sized_args() {
+        let asm = generate(
+            "int f(char *s, int n, char c) { return n; }",
+            Box::new(Aarch64Backend::new(Target::Aarch64LinuxGnu)),
+        );
+        assert!(asm.contains("\tstur x0, [x29, #-24]\n"));
+        assert!(asm.contains("\tstur w1, [x29, #-28]\n"));
+        assert!(asm.contains("\tsturb w2, [x29, #-29]\n"));
     }
 
-    fn generate_push_register_to_stack(&self, register: &str) {
-        println!("\tstr {}, [sp, #-{}]!", register, STACK_ALIGNMENT);
+    #[test]
+    fn generate_aarch64_mod() {
+        let asm = generate(
+            "int main() { return 7 % 3; }",
+            Box::new(Aarch64Backend::new(Target::Aarch64LinuxGnu)),
+        );
+        assert!(asm.contains("\tsdiv x2, x0, x1\n\tmsub x0, x2, x1, x0\n"));
     }
 
-    fn generate_pop_register_from_stack(&self, register: &str) {
-        println!("\tldr {}, [sp], #{}", register, STACK_ALIGNMENT);
+    #[test]
+    fn generate_switch_dispatch() {
+        let dense = generate(
+            "int main() { switch (2) { case 1: case 2: case 3: case 5: return 1; } return 0; }",
+            Box::new(X86_64Backend::new(Target::X86_64LinuxGnu)),
+        );
+        assert!(dense.contains("\tsub rax, 1\n\tcmp rax, 4\n\tja .Lend0\n"));
+        assert!(dense.contains(
+            ".Ltable0:\n\t.long .Lcase0_0-.Ltable0\n\t.long .Lcase0_1-.Ltable0\n\t.long .Lcase0_2-.Ltable0\n\t.long .Lend0-.Ltable0\n\t.long .Lcase0_3-.Ltable0\n"
+        ));
+
+        let sparse = generate(
+            "int main() { switch (2) { case 1: case 20: case 300: case 4000: return 1; default: return 2; } }",
+            Box::new(X86_64Backend::new(Target::X86_64LinuxGnu)),
+        );
+        assert!(!sparse.contains(".Ltable"));
+        assert!(sparse.contains("\tcmp rax, 4000\n\tje .Lcase0_3\n\tjmp .Ldefault0\n"));
     }
 
-    fn generate_comment(&self, comment: &str) {
-        println!("\t; {}", comment);
+    #[test]
+    fn generate_aarch64_large_immediates() {
+        let sparse = generate(
+            "int main() { switch (100000) { case 100000: return 1; case -100000: return 2; } return 0; }",
+            Box::new(Aarch64Backend::new(Target::Aarch64LinuxGnu)),
+        );
+        // 16ビットに収まらない値はmovzとmovkで組み立てる
+        assert!(sparse.contains("\tmovz x2, #34464\n\tmovk x2, #1, lsl #16\n"));
+        assert!(sparse.contains("\tmovz x1, #34464\n\tmovk x1, #1, lsl #16\n\tcmp x0, x1\n"));
+        assert!(sparse.contains(
+            "\tmovz x1, #31072\n\tmovk x1, #65534, lsl #16\n\tmovk x1, #65535, lsl #32\n\tmovk x1, #65535, lsl #48\n\tcmp x0, x1\n"
+        ));
+        assert!(!sparse.contains("#100000"));
+
+        let dense = generate(
+            "int main() { switch (1) { case 100000: case 100001: case 100002: case 100003: return 1; } return 0; }",
+            Box::new(Aarch64Backend::new(Target::Aarch64LinuxGnu)),
+        );
+        assert!(dense.contains(
+            "\tmovz x1, #34464\n\tmovk x1, #1, lsl #16\n\tsub x0, x0, x1\n\tmov x1, #3\n"
+        ));
+    }
+
+    #[test]
+    fn generate_darwin_variadic_call() {
+        let asm = generate(
+            "int printf(char *fmt, ...); int main() { printf(\"%d %d\", 1, 2); return 0; }",
+            Box::new(Aarch64Backend::new(Target::Aarch64AppleDarwin)),
+        );
+        // 可変長引数はレジスタではなくスタックに置く
+        assert!(asm.contains("\tldr x0, [sp, #32]\n\tsub sp, sp, #16\n"));
+        assert!(asm.contains(
+            "\tstr x9, [sp, #0]\n\tldr x9, [sp, #16]\n\tstr x9, [sp, #8]\n\tbl _printf\n"
+        ));
+        assert!(!asm.contains("\tldr x1, "));
+    }
+
+    #[test]
+    fn generate_into_failing_writer() {
+        struct FailingWriter;
+        impl std::io::Write for FailingWriter {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk full"))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let token_list = Tokenizer::new("int main() { return 0; }")
+            .tokenize()
+            .unwrap();
+        let (program_node, string_literals) = Parser::new(token_list).program().unwrap();
+        let code_generator = CodeGenerator::new(
+            program_node,
+            string_literals,
+            Box::new(Aarch64Backend::new(Target::Aarch64AppleDarwin)),
+        );
+        assert!(code_generator.generate(&mut FailingWriter).is_err());
     }
 }
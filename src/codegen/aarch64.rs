@@ -0,0 +1,626 @@
+use std::io::{self, Write};
+
+use crate::parser::{align_to, BinOpType, StringLiteralEntry, Ty};
+
+use super::{Backend, Target};
+
+const FRAME_POINTER_REGISTER: &str = "x29";
+const LINK_REGISTER: &str = "x30";
+const STACK_ALIGNMENT: i32 = 16;
+// AAPCS64で整数引数を渡すレジスタの数
+const ARG_REGISTER_COUNT: usize = 8;
+
+// 引数を渡す場所
+enum ArgLocation {
+    Register(usize),
+    // スタックで渡す引数の領域の先頭からのオフセットと、置くサイズ
+    Stack(i32, i32),
+}
+
+pub struct Aarch64Backend {
+    target: Target,
+}
+
+impl Aarch64Backend {
+    pub fn new(target: Target) -> Self {
+        Self { target }
+    }
+
+    // PC相対でシンボルのアドレスをx0に読み込む
+    fn generate_address_of_symbol(&self, out: &mut dyn Write, symbol: &str) -> io::Result<()> {
+        match self.target {
+            Target::Aarch64AppleDarwin => {
+                writeln!(out, "\tadrp x0, {}@PAGE", symbol)?;
+                writeln!(out, "\tadd  x0, x0, {}@PAGEOFF", symbol)?;
+            }
+            _ => {
+                writeln!(out, "\tadrp x0, {}", symbol)?;
+                writeln!(out, "\tadd  x0, x0, :lo12:{}", symbol)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // movの即値は16ビットに収まる値しか書けないので、大きな値はmovzとmovkで16ビットずつ組み立てる
+    fn generate_load_immediate(
+        &self,
+        out: &mut dyn Write,
+        register: &str,
+        value: i64,
+    ) -> io::Result<()> {
+        if (-0x10000..=0xffff).contains(&value) {
+            return writeln!(out, "\tmov {}, #{}", register, value);
+        }
+        let bits = value as u64;
+        writeln!(out, "\tmovz {}, #{}", register, bits & 0xffff)?;
+        for shift in [16, 32, 48] {
+            let chunk = (bits >> shift) & 0xffff;
+            if chunk != 0 {
+                writeln!(out, "\tmovk {}, #{}, lsl #{}", register, chunk, shift)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // dst = src + value を求める。addとsubの即値は12ビットなので、大きな値はx16に読み込んでから足す
+    fn generate_add_immediate(
+        &self,
+        out: &mut dyn Write,
+        dst: &str,
+        src: &str,
+        value: i64,
+    ) -> io::Result<()> {
+        if (0..0x1000).contains(&value) {
+            writeln!(out, "\tadd {}, {}, #{}", dst, src, value)
+        } else if (-0xfff..0).contains(&value) {
+            writeln!(out, "\tsub {}, {}, #{}", dst, src, -value)
+        } else {
+            self.generate_load_immediate(out, "x16", value)?;
+            writeln!(out, "\tadd {}, {}, x16", dst, src)
+        }
+    }
+
+    // FPからoffsetの位置を読み書きする
+    // ldurやsturのオフセットは9ビットなので、収まらない位置はアドレスをx10に求めてから使う
+    fn generate_frame_access(
+        &self,
+        out: &mut dyn Write,
+        instruction: &str,
+        register: &str,
+        offset: i64,
+    ) -> io::Result<()> {
+        if (0..0x100).contains(&offset) {
+            writeln!(
+                out,
+                "\t{} {}, [{}, #{}]",
+                instruction, register, FRAME_POINTER_REGISTER, offset
+            )
+        } else if (-0x100..0).contains(&offset) {
+            // 負のオフセットはスケールしない形の命令でしか書けない
+            let unscaled = instruction
+                .replacen("ldr", "ldur", 1)
+                .replacen("str", "stur", 1);
+            writeln!(
+                out,
+                "\t{} {}, [{}, #{}]",
+                unscaled, register, FRAME_POINTER_REGISTER, offset
+            )
+        } else {
+            self.generate_add_immediate(out, "x10", FRAME_POINTER_REGISTER, offset)?;
+            writeln!(out, "\t{} {}, [x10]", instruction, register)
+        }
+    }
+
+    fn generate_push_register_to_stack(
+        &self,
+        out: &mut dyn Write,
+        register: &str,
+    ) -> io::Result<()> {
+        writeln!(out, "\tstr {}, [sp, #-{}]!", register, STACK_ALIGNMENT)
+    }
+
+    fn generate_pop_register_from_stack(
+        &self,
+        out: &mut dyn Write,
+        register: &str,
+    ) -> io::Result<()> {
+        writeln!(out, "\tldr {}, [sp], #{}", register, STACK_ALIGNMENT)
+    }
+
+    // スタックで渡す引数1つ分のサイズ
+    // Linuxは8バイトずつ置き、macOSは型のサイズのまま詰めて置く
+    fn stack_arg_size(&self, ty: &Ty) -> i32 {
+        match self.target {
+            Target::Aarch64AppleDarwin if !ty.is_reference_type() => ty.size(),
+            _ => 8,
+        }
+    }
+
+    // 各引数を渡す場所と、スタックで渡す引数が使うサイズ
+    // macOSでは可変長引数をレジスタに空きがあっても全てスタックに8バイトずつ置く
+    fn arg_layout(
+        &self,
+        arg_tys: &[Ty],
+        fixed_arg_count: Option<usize>,
+    ) -> (Vec<ArgLocation>, i32) {
+        let mut locations = vec![];
+        let mut register_count = 0;
+        let mut size = 0;
+        for (i, ty) in arg_tys.iter().enumerate() {
+            let is_variadic_arg = fixed_arg_count.is_some_and(|count| i >= count);
+            if register_count < ARG_REGISTER_COUNT
+                && !(is_variadic_arg && self.target == Target::Aarch64AppleDarwin)
+            {
+                locations.push(ArgLocation::Register(register_count));
+                register_count += 1;
+                continue;
+            }
+            let arg_size = if is_variadic_arg {
+                8
+            } else {
+                self.stack_arg_size(ty)
+            };
+            size = align_to(size, arg_size);
+            locations.push(ArgLocation::Stack(size, arg_size));
+            size += arg_size;
+        }
+
+        (locations, size)
+    }
+
+    // サイズに合わせた読み書きの命令とレジスタの名前
+    fn sized_access(size: i32, index: usize) -> (&'static str, &'static str, String) {
+        match size {
+            1 => ("ldrb", "strb", format!("w{}", index)),
+            4 => ("ldr", "str", format!("w{}", index)),
+            8 => ("ldr", "str", format!("x{}", index)),
+            // 引数はParser::fundef_paramsとParser::primaryで整数かポインタに限っている
+            _ => unreachable!("size: {} is not supported", size),
+        }
+    }
+}
+
+impl Backend for Aarch64Backend {
+    fn text_section(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{}", self.target.text_section())
+    }
+
+    fn global_var(&mut self, out: &mut dyn Write, name: &str, ty: &Ty) -> io::Result<()> {
+        writeln!(out, ".comm {},{}", self.target.symbol(name), ty.size())
+    }
+
+    fn string_literals(
+        &mut self,
+        out: &mut dyn Write,
+        entries: &[StringLiteralEntry],
+    ) -> io::Result<()> {
+        if !entries.is_empty() {
+            writeln!(out, "{}", self.target.cstring_section())?;
+            for st_ent in entries.iter() {
+                writeln!(out, "{}:", st_ent.label)?;
+                writeln!(out, "\t.asciz \"{}\"", st_ent.contents)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        if let Some(trailer) = self.target.trailer() {
+            writeln!(out, "{}", trailer)?;
+        }
+
+        Ok(())
+    }
+
+    fn comment(&mut self, out: &mut dyn Write, comment: &str) -> io::Result<()> {
+        writeln!(out, "\t{} {}", self.target.comment_prefix(), comment)
+    }
+
+    fn prologue(&mut self, out: &mut dyn Write, name: &str, stack_size: i32) -> io::Result<()> {
+        let symbol = self.target.symbol(name);
+        writeln!(out, "\t.globl {}", symbol)?;
+        writeln!(out, "\t.p2align 2")?;
+        writeln!(out, "{}:", symbol)?;
+        self.comment(out, "Store FP & LR to stack")?;
+        writeln!(
+            out,
+            "\tstp {}, {}, [sp, #-16]!",
+            FRAME_POINTER_REGISTER, LINK_REGISTER
+        )?;
+        self.comment(out, "Update FP")?;
+        writeln!(out, "\tmov {}, sp", FRAME_POINTER_REGISTER)?;
+        self.comment(out, "Allocate stack space for local variables & arguments")?;
+        self.generate_add_immediate(out, "sp", "sp", -i64::from(stack_size))?;
+
+        Ok(())
+    }
+
+    fn store_arg(
+        &mut self,
+        out: &mut dyn Write,
+        index: usize,
+        offset: i32,
+        arg_tys: &[Ty],
+    ) -> io::Result<()> {
+        let ty = &arg_tys[index];
+        let (locations, _) = self.arg_layout(arg_tys, None);
+        let register_index = match locations[index] {
+            ArgLocation::Register(register_index) => register_index,
+            ArgLocation::Stack(stack_arg_offset, _) => {
+                // 9番目以降の引数は、呼び出し元がFPとLRの上に置いている
+                let (load, store, register) = Self::sized_access(ty.size(), 9);
+                self.generate_frame_access(out, load, &register, 16 + i64::from(stack_arg_offset))?;
+                self.generate_frame_access(out, store, &register, -i64::from(offset))?;
+                return Ok(());
+            }
+        };
+        // 引数の型のサイズだけ書き込む
+        let (_, store, register) = Self::sized_access(ty.size(), register_index);
+        self.generate_frame_access(out, store, &register, -i64::from(offset))
+    }
+
+    fn epilogue(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        self.comment(out, "Restore FP & LR from stack")?;
+        writeln!(out, "\tmov sp, {}", FRAME_POINTER_REGISTER)?;
+        writeln!(
+            out,
+            "\tldp {}, {}, [sp], #16",
+            FRAME_POINTER_REGISTER, LINK_REGISTER
+        )?;
+        writeln!(out, "\tret")
+    }
+
+    fn return_value(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        self.generate_pop_register_from_stack(out, "x0")
+    }
+
+    fn call(
+        &mut self,
+        out: &mut dyn Write,
+        name: &str,
+        arg_tys: &[Ty],
+        fixed_arg_count: Option<usize>,
+    ) -> io::Result<()> {
+        // 引数は先頭から順に16バイトずつスタックに積まれている
+        let arg_count = arg_tys.len();
+        let arg_slot_offset = |i: usize| STACK_ALIGNMENT * (arg_count - 1 - i) as i32;
+        let (locations, stack_arg_size) = self.arg_layout(arg_tys, fixed_arg_count);
+        for (i, location) in locations.iter().enumerate() {
+            if let ArgLocation::Register(register_index) = location {
+                writeln!(
+                    out,
+                    "\tldr x{}, [sp, #{}]",
+                    register_index,
+                    arg_slot_offset(i)
+                )?;
+            }
+        }
+        // スタックで渡す引数は、呼び出し先から見てspの位置から並ぶように置き直す
+        let stack_arg_area_size = align_to(stack_arg_size, STACK_ALIGNMENT);
+        if stack_arg_area_size != 0 {
+            self.generate_add_immediate(out, "sp", "sp", -i64::from(stack_arg_area_size))?;
+        }
+        for (i, location) in locations.iter().enumerate() {
+            if let ArgLocation::Stack(stack_arg_offset, size) = location {
+                let (_, store, register) = Self::sized_access(*size, 9);
+                writeln!(
+                    out,
+                    "\tldr x9, [sp, #{}]",
+                    stack_arg_area_size + arg_slot_offset(i)
+                )?;
+                writeln!(out, "\t{} {}, [sp, #{}]", store, register, stack_arg_offset)?;
+            }
+        }
+        writeln!(out, "\tbl {}", self.target.symbol(name))?;
+        // 置き直した引数と、元の引数を捨てる
+        let discard_size = stack_arg_area_size + STACK_ALIGNMENT * arg_count as i32;
+        if discard_size != 0 {
+            self.generate_add_immediate(out, "sp", "sp", i64::from(discard_size))?;
+        }
+        // 関数の戻り値はx0に入っている
+        self.generate_push_register_to_stack(out, "x0")?;
+
+        Ok(())
+    }
+
+    fn save_va_registers(&mut self, out: &mut dyn Write, va_area: i32) -> io::Result<()> {
+        // macOSでは可変長引数はスタックで渡されるので、保存するものはない
+        if self.target == Target::Aarch64AppleDarwin {
+            return Ok(());
+        }
+        self.generate_add_immediate(out, "x9", FRAME_POINTER_REGISTER, -i64::from(va_area))?;
+        for i in (0..ARG_REGISTER_COUNT).step_by(2) {
+            writeln!(out, "\tstp x{}, x{}, [x9, #{}]", i, i + 1, 8 * i)?;
+        }
+
+        Ok(())
+    }
+
+    fn va_start(&mut self, out: &mut dyn Write, arg_tys: &[Ty], va_area: i32) -> io::Result<()> {
+        self.comment(out, "va_start")?;
+        self.generate_pop_register_from_stack(out, "x0")?;
+        // 可変長引数は、スタックで渡された固定の引数の次から並んでいる
+        let (_, stack_arg_size) = self.arg_layout(arg_tys, None);
+        self.generate_add_immediate(
+            out,
+            "x1",
+            FRAME_POINTER_REGISTER,
+            16 + i64::from(align_to(stack_arg_size, 8)),
+        )?;
+        writeln!(out, "\tstr x1, [x0]")?;
+        if self.target == Target::Aarch64AppleDarwin {
+            // macOSのva_listは次の引数を指すポインタ
+            return Ok(());
+        }
+        // Linuxのva_listは { __stack, __gr_top, __vr_top, __gr_offs, __vr_offs } の構造体
+        // __gr_offsは、保存領域の末尾からまだ読んでいないレジスタの引数までのオフセット
+        let register_arg_count = arg_tys.len().min(ARG_REGISTER_COUNT);
+        self.generate_add_immediate(out, "x1", FRAME_POINTER_REGISTER, -i64::from(va_area))?;
+        writeln!(out, "\tadd x1, x1, #{}", 8 * ARG_REGISTER_COUNT)?;
+        writeln!(out, "\tstr x1, [x0, #8]")?;
+        // 浮動小数点数の引数はサポートしていないので、レジスタは使い切ったことにする
+        writeln!(out, "\tstr xzr, [x0, #16]")?;
+        writeln!(
+            out,
+            "\tmov w1, #{}",
+            -8 * (ARG_REGISTER_COUNT - register_arg_count) as i32
+        )?;
+        writeln!(out, "\tstr w1, [x0, #24]")?;
+        writeln!(out, "\tstr wzr, [x0, #28]")?;
+
+        Ok(())
+    }
+
+    fn va_arg(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        self.comment(out, "va_arg")?;
+        self.generate_pop_register_from_stack(out, "x0")?;
+        if self.target != Target::Aarch64AppleDarwin {
+            // レジスタで渡された引数が残っていれば保存領域から読む
+            writeln!(out, "\tldr w1, [x0, #24]")?;
+            writeln!(out, "\tcmp w1, #0")?;
+            writeln!(out, "\tb.ge 1f")?;
+            writeln!(out, "\tadd w2, w1, #8")?;
+            writeln!(out, "\tstr w2, [x0, #24]")?;
+            writeln!(out, "\tldr x2, [x0, #8]")?;
+            writeln!(out, "\tadd x2, x2, w1, sxtw")?;
+            writeln!(out, "\tb 2f")?;
+            writeln!(out, "1:")?;
+        }
+        // スタックの引数は8バイトずつ並んでいる
+        writeln!(out, "\tldr x2, [x0]")?;
+        writeln!(out, "\tadd x1, x2, #8")?;
+        writeln!(out, "\tstr x1, [x0]")?;
+        if self.target != Target::Aarch64AppleDarwin {
+            writeln!(out, "2:")?;
+        }
+        self.generate_push_register_to_stack(out, "x2")?;
+
+        Ok(())
+    }
+
+    fn push_num(&mut self, out: &mut dyn Write, n: i32) -> io::Result<()> {
+        self.generate_load_immediate(out, "x2", n.into())?;
+        self.generate_push_register_to_stack(out, "x2")?;
+
+        Ok(())
+    }
+
+    fn push_local_address(&mut self, out: &mut dyn Write, offset: i32) -> io::Result<()> {
+        writeln!(out, "\tmov x0, {}", FRAME_POINTER_REGISTER)?;
+        self.generate_add_immediate(out, "x0", "x0", -i64::from(offset))?;
+        self.generate_push_register_to_stack(out, "x0")?;
+
+        Ok(())
+    }
+
+    fn push_global_address(&mut self, out: &mut dyn Write, name: &str) -> io::Result<()> {
+        match self.target {
+            Target::Aarch64AppleDarwin => {
+                writeln!(out, "\tadrp x0, _{}@GOTPAGE", name)?;
+                writeln!(out, "\tldr x0, [x0, _{}@GOTPAGEOFF]", name)?;
+            }
+            _ => {
+                self.generate_address_of_symbol(out, name)?;
+            }
+        }
+        self.generate_push_register_to_stack(out, "x0")?;
+
+        Ok(())
+    }
+
+    fn push_string_literal_address(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()> {
+        self.generate_address_of_symbol(out, label)?;
+        self.generate_push_register_to_stack(out, "x0")?;
+
+        Ok(())
+    }
+
+    fn dup(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "\tldr x0, [sp]")?;
+        self.generate_push_register_to_stack(out, "x0")
+    }
+
+    fn pop_discard(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "\tadd sp, sp, #{}", STACK_ALIGNMENT)
+    }
+
+    fn join_branch_values(&mut self) {}
+
+    fn load(&mut self, out: &mut dyn Write, ty: &Ty) -> io::Result<()> {
+        self.comment(out, &format!("Load {:?} type value", ty))?;
+        self.generate_pop_register_from_stack(out, "x0")?;
+        match ty.size() {
+            1 => writeln!(out, "\tldrsb x0, [x0]")?,
+            4 => writeln!(out, "\tldrsw x0, [x0]")?,
+            8 => writeln!(out, "\tldr x0, [x0]")?,
+            // voidの値はロードしない。voidのポインタの参照外しはParser::unaryで弾き、
+            // 配列と構造体はCodeGenerator::loadがアドレスのまま扱う
+            _ => unreachable!("ty: {:?} is not supported", ty),
+        }
+        self.generate_push_register_to_stack(out, "x0")?;
+
+        Ok(())
+    }
+
+    fn store(&mut self, out: &mut dyn Write, ty: &Ty) -> io::Result<()> {
+        self.comment(out, &format!("Store {:?} type value", ty))?;
+        self.generate_pop_register_from_stack(out, "x1")?;
+        self.generate_pop_register_from_stack(out, "x0")?;
+        if ty.is_struct_or_union() {
+            // 1バイトずつコピーする
+            for i in 0..ty.size() {
+                writeln!(out, "\tldrb w2, [x1, #{}]", i)?;
+                writeln!(out, "\tstrb w2, [x0, #{}]", i)?;
+            }
+            self.generate_push_register_to_stack(out, "x1")?;
+            return Ok(());
+        }
+        match ty.size() {
+            1 => writeln!(out, "\tstrb w1, [x0]")?,
+            4 => writeln!(out, "\tstr w1, [x0]")?,
+            8 => writeln!(out, "\tstr x1, [x0]")?,
+            // 構造体以外で代入できるのは整数とポインタだけ
+            // 配列への代入はParser::check_assignableで、voidの変数はParser::check_complete_typeで弾いている
+            _ => unreachable!("ty: {:?} is not supported", ty),
+        }
+        // Cでは代入式は代入された値を返す
+        self.generate_push_register_to_stack(out, "x1")?;
+
+        Ok(())
+    }
+
+    fn binop(&mut self, out: &mut dyn Write, op: &BinOpType) -> io::Result<()> {
+        self.generate_pop_register_from_stack(out, "x1")?;
+        self.generate_pop_register_from_stack(out, "x0")?;
+
+        match *op {
+            BinOpType::Add => writeln!(out, "\tadd x0, x0, x1")?,
+            BinOpType::Sub => writeln!(out, "\tsub x0, x0, x1")?,
+            BinOpType::Mul => writeln!(out, "\tmul x0, x0, x1")?,
+            BinOpType::Div => writeln!(out, "\tsdiv x0, x0, x1")?,
+            BinOpType::Mod => {
+                // x0 - (x0 / x1) * x1
+                writeln!(out, "\tsdiv x2, x0, x1")?;
+                writeln!(out, "\tmsub x0, x2, x1, x0")?;
+            }
+            BinOpType::Equal => {
+                writeln!(out, "\tcmp x0, x1")?;
+                writeln!(out, "\tcset x0, EQ")?;
+            }
+            BinOpType::NotEqual => {
+                writeln!(out, "\tcmp x0, x1")?;
+                writeln!(out, "\tcset x0, NE")?;
+            }
+            BinOpType::LessThan => {
+                writeln!(out, "\tcmp x0, x1")?;
+                writeln!(out, "\tcset x0, LT")?;
+            }
+            BinOpType::LessThanOrEqual => {
+                writeln!(out, "\tcmp x0, x1")?;
+                writeln!(out, "\tcset x0, LE")?;
+            }
+            BinOpType::BitAnd => writeln!(out, "\tand x0, x0, x1")?,
+            BinOpType::BitOr => writeln!(out, "\torr x0, x0, x1")?,
+            BinOpType::BitXor => writeln!(out, "\teor x0, x0, x1")?,
+            BinOpType::ShiftLeft => writeln!(out, "\tlsl x0, x0, x1")?,
+            BinOpType::ShiftRight => writeln!(out, "\tasr x0, x0, x1")?,
+        }
+        self.generate_push_register_to_stack(out, "x0")?;
+
+        Ok(())
+    }
+
+    fn bit_not(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        self.generate_pop_register_from_stack(out, "x0")?;
+        writeln!(out, "\tmvn x0, x0")?;
+        self.generate_push_register_to_stack(out, "x0")
+    }
+
+    fn cast(&mut self, out: &mut dyn Write, ty: &Ty) -> io::Result<()> {
+        let extend = match ty.size() {
+            1 => "sxtb",
+            4 => "sxtw",
+            // ポインタは64bitのまま扱う
+            _ => return Ok(()),
+        };
+        self.comment(out, &format!("Cast to {:?}", ty))?;
+        self.generate_pop_register_from_stack(out, "x0")?;
+        writeln!(out, "\t{} x0, w0", extend)?;
+        self.generate_push_register_to_stack(out, "x0")?;
+
+        Ok(())
+    }
+
+    fn label(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()> {
+        writeln!(out, "{}:", label)
+    }
+
+    fn jump(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()> {
+        writeln!(out, "\tb {}", label)
+    }
+
+    fn branch_if_zero(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()> {
+        self.generate_pop_register_from_stack(out, "x0")?;
+        writeln!(out, "\tcmp x0, #0")?;
+        writeln!(out, "\tb.eq {}", label)?;
+
+        Ok(())
+    }
+
+    fn branch_if_not_zero(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()> {
+        self.generate_pop_register_from_stack(out, "x0")?;
+        writeln!(out, "\tcmp x0, #0")?;
+        writeln!(out, "\tb.ne {}", label)?;
+
+        Ok(())
+    }
+
+    fn compare_chain(
+        &mut self,
+        out: &mut dyn Write,
+        cases: &[(i32, String)],
+        default_label: &str,
+    ) -> io::Result<()> {
+        self.generate_pop_register_from_stack(out, "x0")?;
+        for (value, label) in cases {
+            self.generate_load_immediate(out, "x1", (*value).into())?;
+            writeln!(out, "\tcmp x0, x1")?;
+            writeln!(out, "\tb.eq {}", label)?;
+        }
+        writeln!(out, "\tb {}", default_label)?;
+
+        Ok(())
+    }
+
+    fn jump_table(
+        &mut self,
+        out: &mut dyn Write,
+        min_value: i32,
+        labels: &[String],
+        default_label: &str,
+        table_label: &str,
+    ) -> io::Result<()> {
+        self.generate_pop_register_from_stack(out, "x0")?;
+        self.generate_load_immediate(out, "x1", min_value.into())?;
+        writeln!(out, "\tsub x0, x0, x1")?;
+        // 符号なしで比べれば、min_valueより小さい値も範囲外になる
+        self.generate_load_immediate(out, "x1", labels.len() as i64 - 1)?;
+        writeln!(out, "\tcmp x0, x1")?;
+        writeln!(out, "\tb.hi {}", default_label)?;
+        // 表には表の先頭からの相対位置を入れておく
+        writeln!(out, "\tadr x1, {}", table_label)?;
+        writeln!(out, "\tldrsw x2, [x1, x0, lsl #2]")?;
+        writeln!(out, "\tadd x1, x1, x2")?;
+        writeln!(out, "\tbr x1")?;
+        writeln!(out, "\t.p2align 2")?;
+        writeln!(out, "{}:", table_label)?;
+        for label in labels {
+            writeln!(out, "\t.word {}-{}", label, table_label)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use std::io::{self, Write};
+
+use crate::parser::{BinOpType, StringLiteralEntry, Ty};
+
+// ターゲットごとの命令列を出力する
+// CodeGeneratorはスタックマシンとしてASTを辿り、実際の命令の選択はBackendに任せる
+// 式の値は常にスタックに積まれ、各操作はスタックから値を取り出して結果をスタックに積む
+// 命令はoutに書き出し、書き込みに失敗した場合はエラーを返す
+pub trait Backend {
+    /* Data sections */
+    fn text_section(&mut self, out: &mut dyn Write) -> io::Result<()>;
+    fn global_var(&mut self, out: &mut dyn Write, name: &str, ty: &Ty) -> io::Result<()>;
+    fn string_literals(
+        &mut self,
+        out: &mut dyn Write,
+        entries: &[StringLiteralEntry],
+    ) -> io::Result<()>;
+    // アセンブリの末尾に必要なものを出力する
+    fn finish(&mut self, out: &mut dyn Write) -> io::Result<()>;
+
+    fn comment(&mut self, out: &mut dyn Write, comment: &str) -> io::Result<()>;
+
+    /* Functions */
+    // 関数のラベルを出力し、FPを更新してローカル変数の領域を確保する
+    fn prologue(&mut self, out: &mut dyn Write, name: &str, stack_size: i32) -> io::Result<()>;
+    // index番目の引数をFPからoffsetの位置にコピーする
+    // レジスタに入りきらない引数の位置は、それより前の引数の型で決まるので全ての引数の型を受け取る
+    fn store_arg(
+        &mut self,
+        out: &mut dyn Write,
+        index: usize,
+        offset: i32,
+        arg_tys: &[Ty],
+    ) -> io::Result<()>;
+    // スタックを戻して呼び出し元へ戻る
+    fn epilogue(&mut self, out: &mut dyn Write) -> io::Result<()>;
+    // スタックのトップを関数の戻り値のレジスタに取り出す
+    fn return_value(&mut self, out: &mut dyn Write) -> io::Result<()>;
+    // 引数をarg_tysの数だけスタックから取り出して関数を呼び、戻り値をスタックに積む
+    // レジスタに入りきらない引数はスタックに置いて渡す
+    // 可変長引数の関数ならfixed_arg_countに固定の引数の数を受け取る
+    fn call(
+        &mut self,
+        out: &mut dyn Write,
+        name: &str,
+        arg_tys: &[Ty],
+        fixed_arg_count: Option<usize>,
+    ) -> io::Result<()>;
+    // 引数のレジスタをFPからva_areaの位置に保存する
+    fn save_va_registers(&mut self, out: &mut dyn Write, va_area: i32) -> io::Result<()>;
+    // スタックのトップのアドレスにあるva_listを、固定の引数arg_tysの次の引数を指すように初期化する
+    fn va_start(&mut self, out: &mut dyn Write, arg_tys: &[Ty], va_area: i32) -> io::Result<()>;
+    // スタックのトップのアドレスにあるva_listを進め、次の引数のアドレスをスタックに積む
+    fn va_arg(&mut self, out: &mut dyn Write) -> io::Result<()>;
+
+    /* Stack machine */
+    fn push_num(&mut self, out: &mut dyn Write, n: i32) -> io::Result<()>;
+    fn push_local_address(&mut self, out: &mut dyn Write, offset: i32) -> io::Result<()>;
+    fn push_global_address(&mut self, out: &mut dyn Write, name: &str) -> io::Result<()>;
+    fn push_string_literal_address(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()>;
+    // スタックのトップの値を複製して積む
+    fn dup(&mut self, out: &mut dyn Write) -> io::Result<()>;
+    // スタックのトップを捨てる
+    fn pop_discard(&mut self, out: &mut dyn Write) -> io::Result<()>;
+    // 分岐のそれぞれで値を1つ積んだ後、合流した地点で呼ぶ
+    // 実行時に積まれている値は1つなので、命令は出力せずに積んだ値の数え方だけを直す
+    fn join_branch_values(&mut self);
+    // スタックのトップのアドレスからtyのサイズで値を読み込む
+    fn load(&mut self, out: &mut dyn Write, ty: &Ty) -> io::Result<()>;
+    // スタックのトップの値を、その下のアドレスにtyのサイズで書き込み、値を積み直す
+    // 構造体や共用体の場合はスタックのトップが値の代わりにコピー元のアドレスになる
+    fn store(&mut self, out: &mut dyn Write, ty: &Ty) -> io::Result<()>;
+    fn binop(&mut self, out: &mut dyn Write, op: &BinOpType) -> io::Result<()>;
+    // スタックのトップの値の全てのビットを反転する
+    fn bit_not(&mut self, out: &mut dyn Write) -> io::Result<()>;
+    // スタックのトップの値をtyのサイズに切り詰めて符号拡張する
+    fn cast(&mut self, out: &mut dyn Write, ty: &Ty) -> io::Result<()>;
+
+    /* Control flow */
+    fn label(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()>;
+    fn jump(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()>;
+    // スタックのトップを取り出して、0ならlabelへ飛ぶ
+    fn branch_if_zero(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()>;
+    // スタックのトップを取り出して、0でなければlabelへ飛ぶ
+    fn branch_if_not_zero(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()>;
+    // スタックのトップを取り出して、値が一致するcaseのラベルへ順に比べながら飛ぶ
+    // どれとも一致しなければdefault_labelへ飛ぶ
+    fn compare_chain(
+        &mut self,
+        out: &mut dyn Write,
+        cases: &[(i32, String)],
+        default_label: &str,
+    ) -> io::Result<()>;
+    // スタックのトップを取り出して、min_valueからの差を添字にlabelsの表を引いて飛ぶ
+    // 表の範囲外ならdefault_labelへ飛ぶ
+    fn jump_table(
+        &mut self,
+        out: &mut dyn Write,
+        min_value: i32,
+        labels: &[String],
+        default_label: &str,
+        table_label: &str,
+    ) -> io::Result<()>;
+}
@@ -0,0 +1,113 @@
+use crate::parser::Ty;
+
+// 出力するアセンブリの形式を決めるターゲット
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Target {
+    // M1 Mac (Mach-O)
+    #[default]
+    Aarch64AppleDarwin,
+    // Linux (ELF, GNU assembler)
+    Aarch64LinuxGnu,
+    // Linux (ELF, GNU assembler, System V ABI)
+    X86_64LinuxGnu,
+}
+
+impl Target {
+    pub fn from_triple(triple: &str) -> Option<Self> {
+        match triple {
+            "aarch64-apple-darwin" | "arm64-apple-darwin" => Some(Target::Aarch64AppleDarwin),
+            "aarch64-linux-gnu" | "aarch64-unknown-linux-gnu" => Some(Target::Aarch64LinuxGnu),
+            "x86_64-linux-gnu" | "x86_64-unknown-linux-gnu" => Some(Target::X86_64LinuxGnu),
+            _ => None,
+        }
+    }
+
+    // Mach-OではCのシンボルに _ が前置される
+    pub fn symbol(&self, name: &str) -> String {
+        match self {
+            Target::Aarch64AppleDarwin => format!("_{}", name),
+            Target::Aarch64LinuxGnu | Target::X86_64LinuxGnu => name.to_owned(),
+        }
+    }
+
+    // GNU asのAArch64では ; は文の区切りなので、コメントには // を使う
+    pub fn comment_prefix(&self) -> &'static str {
+        match self {
+            Target::Aarch64AppleDarwin => ";",
+            Target::Aarch64LinuxGnu => "//",
+            Target::X86_64LinuxGnu => "#",
+        }
+    }
+
+    pub fn text_section(&self) -> &'static str {
+        match self {
+            Target::Aarch64AppleDarwin => "\t.section\t__TEXT,__text,regular,pure_instructions",
+            Target::Aarch64LinuxGnu | Target::X86_64LinuxGnu => "\t.text",
+        }
+    }
+
+    // ELFでは実行可能スタックを要求しないことを明示する
+    pub fn trailer(&self) -> Option<&'static str> {
+        match self {
+            Target::Aarch64AppleDarwin => None,
+            Target::Aarch64LinuxGnu | Target::X86_64LinuxGnu => {
+                Some("\t.section\t.note.GNU-stack,\"\",@progbits")
+            }
+        }
+    }
+
+    // va_listの型。macOSではただのポインタで、Linuxでは構造体なので同じ大きさの配列として扱う
+    pub fn va_list_ty(&self) -> Ty {
+        let void_ptr = Box::new(Ty::Ptr(Box::new(Ty::Void)));
+        match self {
+            Target::Aarch64AppleDarwin => Ty::Ptr(Box::new(Ty::Char)),
+            Target::Aarch64LinuxGnu => Ty::Array(void_ptr, 4),
+            Target::X86_64LinuxGnu => Ty::Array(void_ptr, 3),
+        }
+    }
+
+    // 可変長引数の関数で、引数のレジスタを保存しておく領域のサイズ
+    // macOSでは可変長引数は全てスタックで渡されるので不要
+    pub fn va_area_size(&self) -> i32 {
+        match self {
+            Target::Aarch64AppleDarwin => 0,
+            Target::Aarch64LinuxGnu => 8 * 8,
+            Target::X86_64LinuxGnu => 6 * 8,
+        }
+    }
+
+    pub fn cstring_section(&self) -> &'static str {
+        match self {
+            Target::Aarch64AppleDarwin => "\t.section\t__TEXT,__cstring,cstring_literals",
+            Target::Aarch64LinuxGnu | Target::X86_64LinuxGnu => "\t.section\t.rodata",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Target;
+
+    #[test]
+    fn parse_triple() {
+        assert_eq!(
+            Target::from_triple("aarch64-apple-darwin"),
+            Some(Target::Aarch64AppleDarwin)
+        );
+        assert_eq!(
+            Target::from_triple("aarch64-linux-gnu"),
+            Some(Target::Aarch64LinuxGnu)
+        );
+        assert_eq!(
+            Target::from_triple("x86_64-linux-gnu"),
+            Some(Target::X86_64LinuxGnu)
+        );
+        assert_eq!(Target::from_triple("riscv64-linux-gnu"), None);
+    }
+
+    #[test]
+    fn symbol_prefix() {
+        assert_eq!(Target::Aarch64AppleDarwin.symbol("main"), "_main");
+        assert_eq!(Target::Aarch64LinuxGnu.symbol("main"), "main");
+    }
+}
@@ -0,0 +1,477 @@
+use std::io::{self, Write};
+
+use crate::parser::{BinOpType, StringLiteralEntry, Ty};
+
+use super::{Backend, Target};
+
+// System V ABIで整数引数を渡すレジスタ
+const ARG_REGISTERS_64: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+const ARG_REGISTERS_32: [&str; 6] = ["edi", "esi", "edx", "ecx", "r8d", "r9d"];
+const ARG_REGISTERS_8: [&str; 6] = ["dil", "sil", "dl", "cl", "r8b", "r9b"];
+
+pub struct X86_64Backend {
+    target: Target,
+    // スタックマシンとして積んでいる値の数
+    // 関数呼び出しの時点でrspを16バイト境界にそろえるために使う
+    depth: usize,
+}
+
+impl X86_64Backend {
+    pub fn new(target: Target) -> Self {
+        Self { target, depth: 0 }
+    }
+
+    fn push(&mut self, out: &mut dyn Write, register: &str) -> io::Result<()> {
+        writeln!(out, "\tpush {}", register)?;
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    fn pop(&mut self, out: &mut dyn Write, register: &str) -> io::Result<()> {
+        writeln!(out, "\tpop {}", register)?;
+        self.depth -= 1;
+
+        Ok(())
+    }
+
+    fn generate_compare(&self, out: &mut dyn Write, set_instruction: &str) -> io::Result<()> {
+        writeln!(out, "\tcmp rax, rdi")?;
+        writeln!(out, "\t{} al", set_instruction)?;
+        writeln!(out, "\tmovzx rax, al")?;
+
+        Ok(())
+    }
+
+    fn arg_register(index: usize, size: i32) -> &'static str {
+        match size {
+            1 => ARG_REGISTERS_8[index],
+            4 => ARG_REGISTERS_32[index],
+            _ => ARG_REGISTERS_64[index],
+        }
+    }
+
+    fn ptr_size(size: i32) -> &'static str {
+        match size {
+            1 => "byte",
+            4 => "dword",
+            _ => "qword",
+        }
+    }
+}
+
+impl Backend for X86_64Backend {
+    fn text_section(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "\t.intel_syntax noprefix")?;
+        writeln!(out, "{}", self.target.text_section())?;
+
+        Ok(())
+    }
+
+    fn global_var(&mut self, out: &mut dyn Write, name: &str, ty: &Ty) -> io::Result<()> {
+        writeln!(out, ".comm {},{}", self.target.symbol(name), ty.size())
+    }
+
+    fn string_literals(
+        &mut self,
+        out: &mut dyn Write,
+        entries: &[StringLiteralEntry],
+    ) -> io::Result<()> {
+        if !entries.is_empty() {
+            writeln!(out, "{}", self.target.cstring_section())?;
+            for st_ent in entries.iter() {
+                writeln!(out, "{}:", st_ent.label)?;
+                writeln!(out, "\t.asciz \"{}\"", st_ent.contents)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        if let Some(trailer) = self.target.trailer() {
+            writeln!(out, "{}", trailer)?;
+        }
+
+        Ok(())
+    }
+
+    fn comment(&mut self, out: &mut dyn Write, comment: &str) -> io::Result<()> {
+        writeln!(out, "\t{} {}", self.target.comment_prefix(), comment)
+    }
+
+    fn prologue(&mut self, out: &mut dyn Write, name: &str, stack_size: i32) -> io::Result<()> {
+        let symbol = self.target.symbol(name);
+        writeln!(out, "\t.globl {}", symbol)?;
+        writeln!(out, "{}:", symbol)?;
+        writeln!(out, "\tpush rbp")?;
+        writeln!(out, "\tmov rbp, rsp")?;
+        writeln!(out, "\tsub rsp, {}", stack_size)?;
+        self.depth = 0;
+
+        Ok(())
+    }
+
+    fn store_arg(
+        &mut self,
+        out: &mut dyn Write,
+        index: usize,
+        offset: i32,
+        arg_tys: &[Ty],
+    ) -> io::Result<()> {
+        let size = arg_tys[index].size();
+        if index < ARG_REGISTERS_64.len() {
+            writeln!(
+                out,
+                "\tmov {} ptr [rbp-{}], {}",
+                Self::ptr_size(size),
+                offset,
+                Self::arg_register(index, size)
+            )?;
+            return Ok(());
+        }
+        // 7番目以降の引数は、呼び出し元が戻りアドレスの上に8バイトずつ積んでいる
+        let stack_arg_offset = 16 + 8 * (index - ARG_REGISTERS_64.len());
+        writeln!(out, "\tmov r10, qword ptr [rbp+{}]", stack_arg_offset)?;
+        let register = match size {
+            1 => "r10b",
+            4 => "r10d",
+            _ => "r10",
+        };
+        writeln!(
+            out,
+            "\tmov {} ptr [rbp-{}], {}",
+            Self::ptr_size(size),
+            offset,
+            register
+        )?;
+
+        Ok(())
+    }
+
+    fn epilogue(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "\tmov rsp, rbp")?;
+        writeln!(out, "\tpop rbp")?;
+        writeln!(out, "\tret")?;
+
+        Ok(())
+    }
+
+    fn return_value(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        self.pop(out, "rax")
+    }
+
+    // System V ABIでは可変長引数も固定の引数と同じように渡す
+    fn call(
+        &mut self,
+        out: &mut dyn Write,
+        name: &str,
+        arg_tys: &[Ty],
+        _fixed_arg_count: Option<usize>,
+    ) -> io::Result<()> {
+        // 引数は先頭から順にスタックに積まれている
+        let arg_count = arg_tys.len();
+        for (i, register) in ARG_REGISTERS_64.iter().take(arg_count).enumerate() {
+            writeln!(
+                out,
+                "\tmov {}, qword ptr [rsp+{}]",
+                register,
+                8 * (arg_count - 1 - i)
+            )?;
+        }
+        // 関数呼び出しの時点でrspは16バイト境界にそろっている必要がある
+        let stack_arg_count = arg_count.saturating_sub(ARG_REGISTERS_64.len());
+        let padding = if (self.depth + stack_arg_count) % 2 == 1 {
+            8
+        } else {
+            0
+        };
+        if padding != 0 {
+            writeln!(out, "\tsub rsp, {}", padding)?;
+        }
+        // 7番目以降の引数は、後ろの引数から積み直して7番目の引数がrspの位置に来るようにする
+        for i in (ARG_REGISTERS_64.len()..arg_count).rev() {
+            let pushed = arg_count - 1 - i;
+            self.push(
+                out,
+                &format!(
+                    "qword ptr [rsp+{}]",
+                    padding + 8 * (arg_count - 1 - i) + 8 * pushed
+                ),
+            )?;
+        }
+        // 可変長引数関数のために、ベクタレジスタで渡す引数の数をalに入れておく
+        writeln!(out, "\tmov rax, 0")?;
+        writeln!(out, "\tcall {}", self.target.symbol(name))?;
+        // 積み直した引数と、元の引数を捨てる
+        let discard_size = padding + 8 * (arg_count + stack_arg_count);
+        if discard_size != 0 {
+            writeln!(out, "\tadd rsp, {}", discard_size)?;
+        }
+        self.depth -= arg_count + stack_arg_count;
+        // 関数の戻り値はraxに入っている
+        self.push(out, "rax")?;
+
+        Ok(())
+    }
+
+    fn save_va_registers(&mut self, out: &mut dyn Write, va_area: i32) -> io::Result<()> {
+        for (i, register) in ARG_REGISTERS_64.iter().enumerate() {
+            writeln!(
+                out,
+                "\tmov qword ptr [rbp-{}], {}",
+                va_area - 8 * i as i32,
+                register
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // va_listは { gp_offset, fp_offset, overflow_arg_area, reg_save_area } の構造体
+    fn va_start(&mut self, out: &mut dyn Write, arg_tys: &[Ty], va_area: i32) -> io::Result<()> {
+        self.comment(out, "va_start")?;
+        self.pop(out, "rax")?;
+        let register_arg_count = arg_tys.len().min(ARG_REGISTERS_64.len());
+        let stack_arg_count = arg_tys.len() - register_arg_count;
+        writeln!(out, "\tmov dword ptr [rax], {}", 8 * register_arg_count)?;
+        // 浮動小数点数の引数はサポートしていないので、レジスタは使い切ったことにする
+        writeln!(out, "\tmov dword ptr [rax+4], {}", 8 * 6 + 16 * 8)?;
+        writeln!(out, "\tlea rdx, [rbp+{}]", 16 + 8 * stack_arg_count)?;
+        writeln!(out, "\tmov qword ptr [rax+8], rdx")?;
+        writeln!(out, "\tlea rdx, [rbp-{}]", va_area)?;
+        writeln!(out, "\tmov qword ptr [rax+16], rdx")?;
+
+        Ok(())
+    }
+
+    // レジスタで渡された引数が残っていれば保存領域から、なければスタックから読む
+    fn va_arg(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        self.comment(out, "va_arg")?;
+        self.pop(out, "rax")?;
+        writeln!(out, "\tmov edx, dword ptr [rax]")?;
+        writeln!(out, "\tcmp edx, {}", 8 * ARG_REGISTERS_64.len())?;
+        writeln!(out, "\tjae 1f")?;
+        writeln!(out, "\tlea edi, [rdx+8]")?;
+        writeln!(out, "\tmov dword ptr [rax], edi")?;
+        writeln!(out, "\tmov rdi, qword ptr [rax+16]")?;
+        writeln!(out, "\tadd rdi, rdx")?;
+        writeln!(out, "\tjmp 2f")?;
+        writeln!(out, "1:")?;
+        writeln!(out, "\tmov rdi, qword ptr [rax+8]")?;
+        writeln!(out, "\tlea rdx, [rdi+8]")?;
+        writeln!(out, "\tmov qword ptr [rax+8], rdx")?;
+        writeln!(out, "2:")?;
+        self.push(out, "rdi")?;
+
+        Ok(())
+    }
+
+    fn push_num(&mut self, out: &mut dyn Write, n: i32) -> io::Result<()> {
+        self.push(out, &n.to_string())
+    }
+
+    fn push_local_address(&mut self, out: &mut dyn Write, offset: i32) -> io::Result<()> {
+        writeln!(out, "\tlea rax, [rbp-{}]", offset)?;
+        self.push(out, "rax")?;
+
+        Ok(())
+    }
+
+    fn push_global_address(&mut self, out: &mut dyn Write, name: &str) -> io::Result<()> {
+        writeln!(out, "\tlea rax, [rip+{}]", self.target.symbol(name))?;
+        self.push(out, "rax")?;
+
+        Ok(())
+    }
+
+    fn push_string_literal_address(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()> {
+        writeln!(out, "\tlea rax, [rip+{}]", label)?;
+        self.push(out, "rax")?;
+
+        Ok(())
+    }
+
+    fn dup(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        self.push(out, "qword ptr [rsp]")
+    }
+
+    fn pop_discard(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "\tadd rsp, 8")?;
+        self.depth -= 1;
+
+        Ok(())
+    }
+
+    fn join_branch_values(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn load(&mut self, out: &mut dyn Write, ty: &Ty) -> io::Result<()> {
+        self.pop(out, "rax")?;
+        match ty.size() {
+            1 => writeln!(out, "\tmovsx rax, byte ptr [rax]")?,
+            4 => writeln!(out, "\tmovsxd rax, dword ptr [rax]")?,
+            8 => writeln!(out, "\tmov rax, [rax]")?,
+            // voidの値はロードしない。voidのポインタの参照外しはParser::unaryで弾き、
+            // 配列と構造体はCodeGenerator::loadがアドレスのまま扱う
+            _ => unreachable!("ty: {:?} is not supported", ty),
+        }
+        self.push(out, "rax")?;
+
+        Ok(())
+    }
+
+    fn store(&mut self, out: &mut dyn Write, ty: &Ty) -> io::Result<()> {
+        self.pop(out, "rdi")?;
+        self.pop(out, "rax")?;
+        if ty.is_struct_or_union() {
+            // 1バイトずつコピーする
+            for i in 0..ty.size() {
+                writeln!(out, "\tmov r8b, [rdi+{}]", i)?;
+                writeln!(out, "\tmov [rax+{}], r8b", i)?;
+            }
+            self.push(out, "rdi")?;
+            return Ok(());
+        }
+        match ty.size() {
+            1 => writeln!(out, "\tmov [rax], dil")?,
+            4 => writeln!(out, "\tmov [rax], edi")?,
+            8 => writeln!(out, "\tmov [rax], rdi")?,
+            // 構造体以外で代入できるのは整数とポインタだけ
+            // 配列への代入はParser::check_assignableで、voidの変数はParser::check_complete_typeで弾いている
+            _ => unreachable!("ty: {:?} is not supported", ty),
+        }
+        // Cでは代入式は代入された値を返す
+        self.push(out, "rdi")?;
+
+        Ok(())
+    }
+
+    fn binop(&mut self, out: &mut dyn Write, op: &BinOpType) -> io::Result<()> {
+        self.pop(out, "rdi")?;
+        self.pop(out, "rax")?;
+
+        match *op {
+            BinOpType::Add => writeln!(out, "\tadd rax, rdi")?,
+            BinOpType::Sub => writeln!(out, "\tsub rax, rdi")?,
+            BinOpType::Mul => writeln!(out, "\timul rax, rdi")?,
+            BinOpType::Div => {
+                writeln!(out, "\tcqo")?;
+                writeln!(out, "\tidiv rdi")?;
+            }
+            BinOpType::Mod => {
+                // idivは余りをrdxに置く
+                writeln!(out, "\tcqo")?;
+                writeln!(out, "\tidiv rdi")?;
+                writeln!(out, "\tmov rax, rdx")?;
+            }
+            BinOpType::Equal => self.generate_compare(out, "sete")?,
+            BinOpType::NotEqual => self.generate_compare(out, "setne")?,
+            BinOpType::LessThan => self.generate_compare(out, "setl")?,
+            BinOpType::LessThanOrEqual => self.generate_compare(out, "setle")?,
+            BinOpType::BitAnd => writeln!(out, "\tand rax, rdi")?,
+            BinOpType::BitOr => writeln!(out, "\tor rax, rdi")?,
+            BinOpType::BitXor => writeln!(out, "\txor rax, rdi")?,
+            BinOpType::ShiftLeft => {
+                writeln!(out, "\tmov rcx, rdi")?;
+                writeln!(out, "\tshl rax, cl")?;
+            }
+            BinOpType::ShiftRight => {
+                writeln!(out, "\tmov rcx, rdi")?;
+                writeln!(out, "\tsar rax, cl")?;
+            }
+        }
+        self.push(out, "rax")?;
+
+        Ok(())
+    }
+
+    fn bit_not(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        self.pop(out, "rax")?;
+        writeln!(out, "\tnot rax")?;
+        self.push(out, "rax")
+    }
+
+    fn cast(&mut self, out: &mut dyn Write, ty: &Ty) -> io::Result<()> {
+        let extend = match ty.size() {
+            1 => "movsx rax, al",
+            4 => "movsxd rax, eax",
+            // ポインタは64bitのまま扱う
+            _ => return Ok(()),
+        };
+        self.comment(out, &format!("Cast to {:?}", ty))?;
+        self.pop(out, "rax")?;
+        writeln!(out, "\t{}", extend)?;
+        self.push(out, "rax")?;
+
+        Ok(())
+    }
+
+    fn label(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()> {
+        writeln!(out, "{}:", label)
+    }
+
+    fn jump(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()> {
+        writeln!(out, "\tjmp {}", label)
+    }
+
+    fn branch_if_zero(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()> {
+        self.pop(out, "rax")?;
+        writeln!(out, "\tcmp rax, 0")?;
+        writeln!(out, "\tje {}", label)?;
+
+        Ok(())
+    }
+
+    fn branch_if_not_zero(&mut self, out: &mut dyn Write, label: &str) -> io::Result<()> {
+        self.pop(out, "rax")?;
+        writeln!(out, "\tcmp rax, 0")?;
+        writeln!(out, "\tjne {}", label)?;
+
+        Ok(())
+    }
+
+    fn compare_chain(
+        &mut self,
+        out: &mut dyn Write,
+        cases: &[(i32, String)],
+        default_label: &str,
+    ) -> io::Result<()> {
+        self.pop(out, "rax")?;
+        for (value, label) in cases {
+            writeln!(out, "\tcmp rax, {}", value)?;
+            writeln!(out, "\tje {}", label)?;
+        }
+        writeln!(out, "\tjmp {}", default_label)?;
+
+        Ok(())
+    }
+
+    fn jump_table(
+        &mut self,
+        out: &mut dyn Write,
+        min_value: i32,
+        labels: &[String],
+        default_label: &str,
+        table_label: &str,
+    ) -> io::Result<()> {
+        self.pop(out, "rax")?;
+        writeln!(out, "\tsub rax, {}", min_value)?;
+        // 符号なしで比べれば、min_valueより小さい値も範囲外になる
+        writeln!(out, "\tcmp rax, {}", labels.len() - 1)?;
+        writeln!(out, "\tja {}", default_label)?;
+        // 表には表の先頭からの相対位置を入れておく
+        writeln!(out, "\tlea rdi, [rip+{}]", table_label)?;
+        writeln!(out, "\tmovsxd rax, dword ptr [rdi+rax*4]")?;
+        writeln!(out, "\tadd rax, rdi")?;
+        writeln!(out, "\tjmp rax")?;
+        writeln!(out, "\t.p2align 2")?;
+        writeln!(out, "{}:", table_label)?;
+        for label in labels {
+            writeln!(out, "\t.long {}-{}", label, table_label)?;
+        }
+
+        Ok(())
+    }
+}
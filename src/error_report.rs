@@ -1,4 +1,195 @@
-pub fn error_at(input: &str, pos: usize, msg: &str) {
-    eprintln!("{}", input);
-    eprintln!("{}^ {}", " ".repeat(pos), msg);
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+// ソースコード中の範囲 [start, end)。バイトオフセットで表す
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, len: usize) -> Self {
+        Self {
+            start,
+            end: start + len,
+        }
+    }
+}
+
+// spanの開始位置の行番号・列番号(1始まり)と、その行の内容
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    pub source_line: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Option<Span>,
+    pub location: Option<Location>,
+    pub message: String,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error<S: Into<String>>(message: S) -> Self {
+        Self {
+            severity: Severity::Error,
+            span: None,
+            location: None,
+            message: message.into(),
+            notes: vec![],
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_location(mut self, location: Location) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    // file:line:col: error: message の形式で表示し、該当する行とその列に ^ を付けて表示する
+    pub fn render(&self, file_name: &str) -> String {
+        let mut rendered = String::new();
+        match self.location {
+            Some(ref location) => {
+                rendered.push_str(&format!(
+                    "{}:{}:{}: {}: {}\n",
+                    file_name, location.line, location.column, self.severity, self.message
+                ));
+                // タブはそのまま残して、^ の位置がずれないようにする
+                let indent = location
+                    .source_line
+                    .chars()
+                    .take(location.column - 1)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect::<String>();
+                rendered.push_str(&format!("{}\n{}^\n", location.source_line, indent));
+            }
+            None => rendered.push_str(&format!(
+                "{}: {}: {}\n",
+                file_name, self.severity, self.message
+            )),
+        }
+        for note in self.notes.iter() {
+            rendered.push_str(&format!("note: {}\n", note));
+        }
+
+        rendered
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+impl From<std::io::Error> for Diagnostic {
+    fn from(err: std::io::Error) -> Self {
+        Diagnostic::error(format!("failed to write assembly: {}", err))
+    }
+}
+
+// コンパイル中に見つかったエラーの一覧
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Diagnostics {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn render(&self, file_name: &str) -> String {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.render(file_name))
+            .collect()
+    }
+}
+
+impl From<Diagnostic> for Diagnostics {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Self {
+            diagnostics: vec![diagnostic],
+        }
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in self.diagnostics.iter() {
+            writeln!(f, "{}", diagnostic)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, Location, Span};
+
+    #[test]
+    fn render_with_location() {
+        let diagnostic = Diagnostic::error("Unexpected Token")
+            .with_span(Span::new(8, 1))
+            .with_location(Location {
+                line: 2,
+                column: 5,
+                source_line: "int @;".to_owned(),
+            });
+        assert_eq!(
+            diagnostic.render("tmp.c"),
+            "tmp.c:2:5: error: Unexpected Token\nint @;\n    ^\n"
+        );
+    }
+
+    #[test]
+    fn render_keeps_tabs_before_caret() {
+        let diagnostic = Diagnostic::error("undefined variable: x").with_location(Location {
+            line: 1,
+            column: 9,
+            source_line: "\treturn x;".to_owned(),
+        });
+        assert_eq!(
+            diagnostic.render("tmp.c"),
+            "tmp.c:1:9: error: undefined variable: x\n\treturn x;\n\t       ^\n"
+        );
+    }
+
+    #[test]
+    fn render_notes() {
+        let diagnostic = Diagnostic::error("undefined variable: x").with_note("declare x first");
+        assert_eq!(
+            diagnostic.render("tmp.c"),
+            "tmp.c: error: undefined variable: x\nnote: declare x first\n"
+        );
+    }
 }
@@ -0,0 +1,186 @@
+pub mod codegen;
+pub mod error_report;
+pub mod parser;
+pub mod tokenizer;
+
+use crate::{
+    codegen::{CodeGenerator, Target},
+    parser::{Node, Parser, StringLiteralEntry, DEFAULT_ERROR_LIMIT},
+    tokenizer::{TokenList, Tokenizer},
+};
+pub use error_report::{Diagnostic, Diagnostics};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CompileOptions {
+    pub target: Target,
+    // この数のエラーを報告したら解析を打ち切る。0なら上限なし
+    pub error_limit: usize,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            target: Target::default(),
+            error_limit: DEFAULT_ERROR_LIMIT,
+        }
+    }
+}
+
+// ソースコードをトークン列に分割する
+pub fn tokenize(source: &str) -> Result<TokenList<'_>, Diagnostics> {
+    Ok(Tokenizer::new(source).tokenize()?)
+}
+
+// ソースコードをASTと文字列リテラルの一覧に変換する
+pub fn parse(
+    source: &str,
+    options: &CompileOptions,
+) -> Result<(Vec<Node>, Vec<StringLiteralEntry>), Diagnostics> {
+    let token_list = tokenize(source)?;
+    Parser::new(token_list)
+        .with_error_limit(options.error_limit)
+        .with_target(options.target)
+        .program()
+}
+
+// ソースコードをoptions.targetのアセンブリに変換する
+pub fn compile(source: &str, options: &CompileOptions) -> Result<String, Diagnostics> {
+    let (program_node, string_literals) = parse(source, options)?;
+    let code_generator = CodeGenerator::new(
+        program_node,
+        string_literals,
+        codegen::backend_for(options.target),
+    );
+    let mut out = vec![];
+    code_generator.generate(&mut out)?;
+
+    Ok(String::from_utf8(out).expect("generated assembly should be valid UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{codegen::Target, compile, parse, tokenize, CompileOptions};
+    use crate::{parser::Ast, tokenizer::TokenKind};
+
+    #[test]
+    fn tokenize_source() {
+        let mut token_list = tokenize("int main").unwrap();
+        assert_eq!(token_list.next().unwrap().kind, TokenKind::Int);
+        assert_eq!(token_list.next().unwrap().kind, TokenKind::Ident);
+        assert!(token_list.at_end());
+    }
+
+    #[test]
+    fn parse_source() {
+        let (program, string_literals) = parse(
+            "int x; int main() { return 0; }",
+            &CompileOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(program.len(), 2);
+        assert!(matches!(program[1].ast, Ast::Fundef { ref name, .. } if name == "main"));
+        assert!(string_literals.is_empty());
+    }
+
+    #[test]
+    fn compile_for_each_target() {
+        let source = "int main() { return 42; }";
+        let darwin = compile(source, &CompileOptions::default()).unwrap();
+        assert!(darwin.contains("_main:"));
+        let linux = compile(
+            source,
+            &CompileOptions {
+                target: Target::X86_64LinuxGnu,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(linux.contains("\nmain:"));
+    }
+
+    #[test]
+    fn undefined_variable_is_diagnostic() {
+        let diagnostics = compile("int main() { return x; }", &CompileOptions::default())
+            .unwrap_err()
+            .diagnostics;
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "undefined variable: x");
+        assert_eq!(diagnostics[0].span.unwrap().start, 20);
+    }
+
+    #[test]
+    fn diagnostic_has_line_and_column() {
+        let source = "int main() {\n  \"日本語\"; return x;\n}\n";
+        let diagnostics = compile(source, &CompileOptions::default())
+            .unwrap_err()
+            .diagnostics;
+        assert_eq!(
+            diagnostics[0].render("tmp.c"),
+            "tmp.c:2:17: error: undefined variable: x\n  \"日本語\"; return x;\n                ^\n"
+        );
+    }
+
+    #[test]
+    fn unexpected_eof_is_diagnostic() {
+        let diagnostics = compile("int main() { return 1;", &CompileOptions::default())
+            .unwrap_err()
+            .diagnostics;
+        assert_eq!(diagnostics[0].message, "Unexpected EOF, Expected RBrace");
+    }
+
+    #[test]
+    fn assignment_to_rvalue_is_diagnostic() {
+        let diagnostics = compile("int main() { 1 = 2; }", &CompileOptions::default())
+            .unwrap_err()
+            .diagnostics;
+        assert_eq!(
+            diagnostics[0].message,
+            "Left side of assignment is not a lvalue"
+        );
+    }
+
+    #[test]
+    fn reports_multiple_syntax_errors() {
+        let source =
+            "int main() {\n  1 +;\n  if (1 { 2; }\n  return x;\n}\nint 3;\nint f() { return; }\n";
+        let diagnostics = compile(source, &CompileOptions::default())
+            .unwrap_err()
+            .diagnostics;
+        let lines = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.location.as_ref().unwrap().line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![2, 3, 4, 6, 7]);
+    }
+
+    #[test]
+    fn unexpected_eof_is_reported_once() {
+        let diagnostics = compile(
+            "int main() { if (1) { while (1) { 1 +; }",
+            &CompileOptions::default(),
+        )
+        .unwrap_err()
+        .diagnostics;
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[1].message, "Unexpected EOF, Expected RBrace");
+    }
+
+    #[test]
+    fn error_limit_stops_parsing() {
+        let source = "int main() { 1 +; 2 +; 3 +; 4 +; }";
+        let options = CompileOptions {
+            error_limit: 2,
+            ..Default::default()
+        };
+        let diagnostics = compile(source, &options).unwrap_err().diagnostics;
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[1].notes.len(), 1);
+
+        let unlimited = CompileOptions {
+            error_limit: 0,
+            ..Default::default()
+        };
+        let diagnostics = compile(source, &unlimited).unwrap_err().diagnostics;
+        assert_eq!(diagnostics.len(), 4);
+    }
+}
@@ -1,31 +1,108 @@
-mod codegen;
-mod error_report;
-mod parser;
-mod tokenizer;
+use compiler_book_rs::{codegen::Target, compile, CompileOptions};
+use std::{
+    env,
+    io::{self, Read, Write},
+    path::PathBuf,
+    process::exit,
+};
 
-use crate::{parser::Parser, tokenizer::Tokenizer};
-use std::{env, io::Read, path::PathBuf};
+struct Args {
+    target: Target,
+    error_limit: usize,
+    input: String,
+    // 指定されなければ標準出力に書き出す
+    output: Option<String>,
+}
 
 fn main() {
     let args = env::args().collect::<Vec<_>>();
-    if args.len() != 2 {
-        println!("Usage: {} <c-file>", args[0]);
-        return;
+    let Args {
+        target,
+        error_limit,
+        input,
+        output,
+    } = match parse_args(&args[1..]) {
+        Some(parsed) => parsed,
+        None => {
+            println!(
+                "Usage: {} [--target <triple>] [--error-limit <n>] [-o <output>] <c-file>",
+                args[0]
+            );
+            println!(
+                "Supported targets: aarch64-apple-darwin, aarch64-linux-gnu, x86_64-linux-gnu"
+            );
+            return;
+        }
+    };
+
+    let program = match read_program(&input) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("failed to read {}: {}", input, err);
+            exit(1);
+        }
+    };
+    let assembly = match compile(
+        &program,
+        &CompileOptions {
+            target,
+            error_limit,
+        },
+    ) {
+        Ok(assembly) => assembly,
+        Err(diagnostics) => {
+            eprint!("{}", diagnostics.render(&input));
+            exit(1);
+        }
+    };
+    let result = match output {
+        Some(path) => std::fs::write(path, assembly),
+        None => io::stdout().write_all(assembly.as_bytes()),
+    };
+    if let Err(err) = result {
+        eprintln!("failed to write assembly: {}", err);
+        exit(1);
+    }
+}
+
+fn parse_args(args: &[String]) -> Option<Args> {
+    let mut target = Target::default();
+    let mut error_limit = CompileOptions::default().error_limit;
+    let mut input = None;
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--target" {
+            target = Target::from_triple(args.next()?)?;
+        } else if let Some(triple) = arg.strip_prefix("--target=") {
+            target = Target::from_triple(triple)?;
+        } else if arg == "--error-limit" {
+            error_limit = args.next()?.parse().ok()?;
+        } else if let Some(limit) = arg.strip_prefix("--error-limit=") {
+            error_limit = limit.parse().ok()?;
+        } else if arg == "-o" {
+            output = Some(args.next()?.clone());
+        } else if input.is_none() {
+            input = Some(arg.clone());
+        } else {
+            return None;
+        }
     }
 
-    let program = read_program(args[1].clone());
-    let token_list = Tokenizer::new(&program).tokenize();
-    let (program_node, string_literals) = Parser::new(token_list).program();
-    let code_generator = codegen::CodeGenerator::new(program_node, string_literals);
-    code_generator.generate();
+    input.map(|input| Args {
+        target,
+        error_limit,
+        input,
+        output,
+    })
 }
 
-fn read_program<P: Into<PathBuf>>(path: P) -> String {
-    let mut file = std::fs::File::open(path.into()).unwrap();
+fn read_program<P: Into<PathBuf>>(path: P) -> io::Result<String> {
+    let mut file = std::fs::File::open(path.into())?;
     let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap();
+    file.read_to_string(&mut contents)?;
     if !contents.ends_with("\n") {
         contents.push('\n');
     }
-    contents
+    Ok(contents)
 }
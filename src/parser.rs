@@ -4,62 +4,158 @@ mod var_env;
 
 pub use node::{Ast, BinOpType, Node};
 
-use crate::tokenizer::{TokenKind, TokenList};
-pub use ty::Ty;
+use crate::{
+    codegen::Target,
+    error_report::{Diagnostic, Diagnostics, Span},
+    tokenizer::{TokenKind, TokenList},
+};
+pub use ty::{align_to, Member, StructRef, Ty};
 pub use var_env::StringLiteralEntry;
 
-use self::var_env::{GlobalVarInfo, LocalVarInfo, VarEnvironment, VarInfo};
+use self::var_env::{FunctionInfo, GlobalVarInfo, LocalVarInfo, VarEnvironment, VarInfo};
+
+// 報告するエラーの数の上限。0なら上限なし
+pub const DEFAULT_ERROR_LIMIT: usize = 20;
+
+// 関数の仮引数。プロトタイプでは名前を省略できる
+struct Param {
+    name: Option<String>,
+    span: Span,
+    ty: Ty,
+}
+
+// 読んでいるswitch文に現れたcaseの値とdefaultの有無
+#[derive(Default)]
+struct SwitchCases {
+    values: Vec<i32>,
+    has_default: bool,
+}
 
 pub struct Parser<'a> {
     token_list: TokenList<'a>,
+    diagnostics: Vec<Diagnostic>,
+    error_limit: usize,
+    // エラーの上限に達したか、入力の終わりでエラーになって解析を打ち切った
+    aborted: bool,
+    // va_listの型や可変長引数の渡し方はターゲットによって異なる
+    target: Target,
+    // 読んでいる関数の宣言
+    current_function: Option<FunctionInfo>,
+    // 読んでいる関数が可変長引数の関数なら、引数のレジスタを保存する領域のoffset
+    va_area: Option<i32>,
+    // 読んでいる文を囲むループの数。0ならcontinueは使えない
+    loop_depth: usize,
+    // 読んでいる文を囲む一番内側のswitch文
+    switch_cases: Option<SwitchCases>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(token_list: TokenList<'a>) -> Parser<'a> {
-        Self { token_list }
+        Self {
+            token_list,
+            diagnostics: vec![],
+            error_limit: DEFAULT_ERROR_LIMIT,
+            aborted: false,
+            target: Target::default(),
+            current_function: None,
+            va_area: None,
+            loop_depth: 0,
+            switch_cases: None,
+        }
+    }
+
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_error_limit(mut self, error_limit: usize) -> Self {
+        self.error_limit = error_limit;
+        self
     }
 
     /* Lexing Programs */
-    pub fn program(&mut self) -> (Vec<Node>, Vec<StringLiteralEntry>) {
+    pub fn program(&mut self) -> Result<(Vec<Node>, Vec<StringLiteralEntry>), Diagnostics> {
         let mut nodes = vec![];
         let mut var_env = VarEnvironment::new();
+        var_env.add_typedef("va_list", self.target.va_list_ty());
         while !self.token_list.at_end() {
-            nodes.push(self.top_level(&mut var_env));
+            match self.top_level(&mut var_env) {
+                Ok(Some(node)) => nodes.push(node),
+                Ok(None) => {}
+                Err(diagnostic) => {
+                    if self.recover(diagnostic).is_err() {
+                        break;
+                    }
+                    self.synchronize_top_level();
+                }
+            }
         }
 
-        (nodes, var_env.string_literals)
+        if self.diagnostics.is_empty() {
+            Ok((nodes, var_env.string_literals))
+        } else {
+            Err(Diagnostics {
+                diagnostics: std::mem::take(&mut self.diagnostics),
+            })
+        }
     }
 
-    fn top_level(&mut self, var_env: &mut VarEnvironment) -> Node {
-        self.token_list.expect_kind(&TokenKind::Int);
-        let mut ty = Ty::Int;
+    // 関数定義かグローバル変数の定義を読む
+    // struct s { ... }; のような型の宣言や、プロトタイプ、externの宣言だけならNoneを返す
+    fn top_level(&mut self, var_env: &mut VarEnvironment) -> Result<Option<Node>, Diagnostic> {
+        if self.token_list.try_consume(&TokenKind::Typedef).is_some() {
+            self.typedef(var_env)?;
+            return Ok(None);
+        }
+        let is_extern = self.token_list.try_consume(&TokenKind::Extern).is_some();
+        let mut ty = match self.declspec(var_env)? {
+            Some(ty) => ty,
+            None => {
+                return Err(Diagnostic::error("Unexpected Token, Expected type name")
+                    .with_span(self.token_list.current_span()));
+            }
+        };
+        if self.token_list.try_consume(&TokenKind::Semicolon).is_some() {
+            return Ok(None);
+        }
         ty = self.type_prefix(&ty);
-        let ident_name = self.token_list.expect_kind(&TokenKind::Ident).str.unwrap();
+        let ident_tok = self.token_list.expect_kind(&TokenKind::Ident)?;
+        let ident_span = ident_tok.span();
+        let ident_name = ident_tok.str.unwrap();
         // 次のトークンをのぞいてみて ( があれば、関数宣言, なければ変数宣言
-        let next_token = self.token_list.peek().unwrap();
-        if next_token.kind == TokenKind::LParen {
-            // assign offsets to local variables
-            // スタックのトップには、FPとLRが保存されているので、-16以降が変数領域
-            var_env.clear_local_variables();
-            let mut function_scope_var_env = var_env;
-            let args = self.fundef_args(&mut function_scope_var_env);
-            let body = self.fundef_body(&mut function_scope_var_env);
-            let stack_size = function_scope_var_env.stack_size();
-
-            Node::new(
-                Ast::Fundef {
-                    name: ident_name,
-                    args,
-                    body,
-                    stack_size,
-                },
-                None,
-            )
+        if matches!(self.token_list.peek(), Some(next_token) if next_token.kind == TokenKind::LParen)
+        {
+            var_env.enter_function();
+            let fundef = self.fundef(ident_name, ident_span, ty, var_env);
+            // エラーで抜けた場合も、後に続く宣言はグローバルなスコープで読む
+            var_env.leave_function();
+
+            fundef
         } else {
-            ty = self.type_suffix(&ty);
-            self.token_list.expect_kind(&TokenKind::Semicolon);
-            var_env.add_global_var(&ident_name, ty.clone());
-            Node::new(Ast::GlobalVarDef(ident_name, ty), None)
+            ty = self.type_suffix(&ty, ident_span, var_env)?;
+            self.token_list.expect_kind(&TokenKind::Semicolon)?;
+            // 同じ名前の変数を宣言し直すなら、型が一致していなければならない
+            if let Some(declared) = var_env.resolve_global_var(&ident_name) {
+                if declared.ty != ty {
+                    return Err(Diagnostic::error(format!(
+                        "conflicting types for '{}'",
+                        ident_name
+                    ))
+                    .with_span(ident_span)
+                    .with_note(self.declared_at(&ident_name, declared.span)));
+                }
+            } else {
+                Self::check_not_redeclared(&ident_name, ident_span, var_env)?;
+            }
+            if is_extern {
+                // 他のファイルで定義される変数なので、領域は確保しない
+                var_env.add_global_var(&ident_name, ty, ident_span);
+                return Ok(None);
+            }
+            Self::check_complete_type(&ty, ident_span)?;
+            var_env.add_global_var(&ident_name, ty.clone(), ident_span);
+            Ok(Some(Node::new(Ast::GlobalVarDef(ident_name, ty), None)))
         }
     }
 
@@ -74,514 +170,2327 @@ impl<'a> Parser<'a> {
     }
 
     // 識別子の後につづく[]のような型に影響をあたえる後置を読む
-    fn type_suffix(&mut self, base_ty: &Ty) -> Ty {
+    // spanは大きすぎる配列を報告する宣言子の位置
+    fn type_suffix(
+        &mut self,
+        base_ty: &Ty,
+        span: Span,
+        var_env: &mut VarEnvironment,
+    ) -> Result<Ty, Diagnostic> {
         let mut ty = base_ty.clone();
         let mut array_dimens = vec![];
         while self.token_list.try_consume(&TokenKind::LBracket).is_some() {
-            let dimen = self.token_list.expect_num();
+            let dimen_span = self.token_list.current_span();
+            let dimen = self.const_expr(var_env)?;
+            if dimen < 0 {
+                return Err(Diagnostic::error("Array has negative size").with_span(dimen_span));
+            }
             array_dimens.push(dimen);
-            self.token_list.expect_kind(&TokenKind::RBracket);
+            self.token_list.expect_kind(&TokenKind::RBracket)?;
         }
         for dimen in array_dimens.iter().rev() {
             ty = Ty::Array(Box::new(ty), *dimen);
+            if ty.checked_size().is_none() {
+                return Err(Diagnostic::error("Array is too large").with_span(span));
+            }
         }
 
-        ty
+        Ok(ty)
     }
 
-    fn fundef_args(&mut self, var_env: &mut VarEnvironment) -> Vec<Node> {
-        let mut args = vec![];
-        self.token_list.expect_kind(&TokenKind::LParen);
-        // 最大6つまでの引数をサポートする
-        let mut paren_consumed = false;
-        for _ in 1..=6 {
-            if self.token_list.try_consume(&TokenKind::RParen).is_none() {
-                self.token_list.expect_kind(&TokenKind::Int);
-                let name = self.token_list.expect_kind(&TokenKind::Ident).str.unwrap();
-                let arg_var_info = var_env.add_local_var(&name, Ty::Int);
-                args.push(Node::new(
-                    Ast::LocalVar {
-                        name,
-                        offset: arg_var_info.offset,
-                    },
-                    Some(arg_var_info.ty),
-                ));
-                if self.token_list.try_consume(&TokenKind::RParen).is_none() {
-                    self.token_list.expect_kind(&TokenKind::Comma);
-                } else {
-                    paren_consumed = true;
+    // int, char, struct, union, enum ... のような型名を読む。型名でなければNoneを返す
+    fn declspec(&mut self, var_env: &mut VarEnvironment) -> Result<Option<Ty>, Diagnostic> {
+        if self.token_list.try_consume(&TokenKind::Int).is_some() {
+            Ok(Some(Ty::Int))
+        } else if self.token_list.try_consume(&TokenKind::Char).is_some() {
+            Ok(Some(Ty::Char))
+        } else if self.token_list.try_consume(&TokenKind::Void).is_some() {
+            Ok(Some(Ty::Void))
+        } else if self.token_list.try_consume(&TokenKind::Struct).is_some() {
+            Ok(Some(self.struct_decl(var_env, false)?))
+        } else if self.token_list.try_consume(&TokenKind::Union).is_some() {
+            Ok(Some(self.struct_decl(var_env, true)?))
+        } else if self.token_list.try_consume(&TokenKind::Enum).is_some() {
+            Ok(Some(self.enum_decl(var_env)?))
+        } else if let Some(ty) = self.typedef_name(var_env) {
+            self.token_list.next();
+            Ok(Some(ty))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // 次のトークンがtypedefで宣言した型の名前ならその型を返す
+    fn typedef_name(&self, var_env: &VarEnvironment) -> Option<Ty> {
+        match self.token_list.peek() {
+            Some(token) if token.kind == TokenKind::Ident => {
+                var_env.resolve_typedef(token.str.as_ref().unwrap())
+            }
+            _ => None,
+        }
+    }
+
+    // n個先のトークンが型名の始まりかどうか
+    // 識別子が型名か変数名かはtypedefの宣言を見て決める
+    fn is_typename(&self, n: usize, var_env: &VarEnvironment) -> bool {
+        match self.token_list.peek_nth(n) {
+            Some(token) => match token.kind {
+                TokenKind::Int
+                | TokenKind::Char
+                | TokenKind::Void
+                | TokenKind::Struct
+                | TokenKind::Union
+                | TokenKind::Enum => true,
+                TokenKind::Ident => var_env
+                    .resolve_typedef(token.str.as_ref().unwrap())
+                    .is_some(),
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
+    // キャストやsizeofの ( ) の中の、変数名のない型を読む
+    fn type_name(&mut self, var_env: &mut VarEnvironment) -> Result<Ty, Diagnostic> {
+        let span = self.token_list.current_span();
+        let ty = match self.declspec(var_env)? {
+            Some(ty) => ty,
+            None => {
+                return Err(Diagnostic::error("Unexpected Token, Expected type name")
+                    .with_span(self.token_list.current_span()));
+            }
+        };
+        let ty = self.type_prefix(&ty);
+        self.type_suffix(&ty, span, var_env)
+    }
+
+    // typedef の後に続く 型 名前 を読んで、名前を型として登録する
+    fn typedef(&mut self, var_env: &mut VarEnvironment) -> Result<(), Diagnostic> {
+        let base_ty = match self.declspec(var_env)? {
+            Some(ty) => ty,
+            None => {
+                return Err(Diagnostic::error("Unexpected Token, Expected type name")
+                    .with_span(self.token_list.current_span()));
+            }
+        };
+        loop {
+            let ty = self.type_prefix(&base_ty);
+            let name_tok = self.token_list.expect_kind(&TokenKind::Ident)?;
+            let name_span = name_tok.span();
+            let name = name_tok.str.unwrap();
+            let ty = self.type_suffix(&ty, name_span, var_env)?;
+            var_env.add_typedef(&name, ty);
+            if self.token_list.try_consume(&TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        self.token_list.expect_kind(&TokenKind::Semicolon)?;
+
+        Ok(())
+    }
+
+    // struct または union の後に続く タグ { メンバの宣言 } を読む
+    // タグとメンバの宣言のどちらかは省略できる
+    fn struct_decl(
+        &mut self,
+        var_env: &mut VarEnvironment,
+        is_union: bool,
+    ) -> Result<Ty, Diagnostic> {
+        let span = self.token_list.current_span();
+        let new_struct_ref = |tag: Option<String>| {
+            if is_union {
+                StructRef::new_union(tag)
+            } else {
+                StructRef::new(tag)
+            }
+        };
+        let tag_tok = self.token_list.try_consume(&TokenKind::Ident);
+        let tag_span = tag_tok.as_ref().map(|tag_tok| tag_tok.span());
+        let tag = tag_tok.map(|tag_tok| tag_tok.str.unwrap());
+        let has_body = matches!(self.token_list.peek(), Some(next_token) if next_token.kind == TokenKind::LBrace);
+        // メンバの宣言があれば今のスコープのタグを定義し、外側のスコープの同じタグは隠す
+        let declared = tag.as_ref().and_then(|tag| {
+            if has_body {
+                var_env.resolve_struct_tag_in_current_scope(tag)
+            } else {
+                var_env.resolve_struct_tag(tag)
+            }
+        });
+        // 構造体と共用体は同じタグの名前空間を使う
+        if let (Some(declared), Some(tag_span)) = (&declared, tag_span) {
+            if declared.is_union() != is_union {
+                return Err(Diagnostic::error(format!(
+                    "Use of {} with tag type that does not match previous declaration {:?}",
+                    tag.unwrap(),
+                    declared
+                ))
+                .with_span(tag_span));
+            }
+        }
+        if let Some(ref tag) = tag {
+            if !has_body {
+                // まだ宣言されていないタグは、後で定義される構造体として扱う
+                let struct_ref = declared.unwrap_or_else(|| {
+                    let struct_ref = new_struct_ref(Some(tag.clone()));
+                    var_env.add_struct_tag(tag, struct_ref.clone());
+                    struct_ref
+                });
+                return Ok(struct_ref.ty());
+            }
+        }
+
+        self.token_list.expect_kind(&TokenKind::LBrace)?;
+        // 同じスコープで定義済みのタグは定義し直せないが、続くエラーを避けるためにメンバは読んでおく
+        let is_redefinition = matches!(&declared, Some(declared) if declared.is_complete());
+        // メンバから自身へのポインタを参照できるように、メンバを読む前にタグを登録しておく
+        let struct_ref = match (tag.clone(), declared) {
+            (Some(_), Some(declared)) if !is_redefinition => declared,
+            (Some(tag), Some(_)) => new_struct_ref(Some(tag)),
+            (Some(ref tag), None) => {
+                let struct_ref = new_struct_ref(Some(tag.clone()));
+                var_env.add_struct_tag(tag, struct_ref.clone());
+                struct_ref
+            }
+            (None, _) => new_struct_ref(None),
+        };
+        let mut members: Vec<(String, Ty)> = vec![];
+        while self.token_list.try_consume(&TokenKind::RBrace).is_none() {
+            if self.token_list.at_end() {
+                self.token_list.expect_kind(&TokenKind::RBrace)?;
+            }
+            let base_ty = match self.declspec(var_env)? {
+                Some(ty) => ty,
+                None => {
+                    return Err(Diagnostic::error("Unexpected Token, Expected type name")
+                        .with_span(self.token_list.current_span()));
+                }
+            };
+            loop {
+                let mut ty = self.type_prefix(&base_ty);
+                let name_tok = self.token_list.expect_kind(&TokenKind::Ident)?;
+                let name_span = name_tok.span();
+                let name = name_tok.str.unwrap();
+                ty = self.type_suffix(&ty, name_span, var_env)?;
+                Self::check_complete_type(&ty, name_span)?;
+                if members.iter().any(|(member_name, _)| *member_name == name) {
+                    return Err(Diagnostic::error(format!("Duplicate member {}", name))
+                        .with_span(name_span));
+                }
+                members.push((name, ty));
+                if self.token_list.try_consume(&TokenKind::Comma).is_none() {
                     break;
                 }
+            }
+            self.token_list.expect_kind(&TokenKind::Semicolon)?;
+        }
+        if is_redefinition {
+            let kind = if is_union { "union" } else { "struct" };
+            return Err(
+                Diagnostic::error(format!("Redefinition of {} {}", kind, tag.unwrap()))
+                    .with_span(tag_span.unwrap()),
+            );
+        }
+        if !struct_ref.define(members) {
+            let kind = if is_union { "Union" } else { "Struct" };
+            return Err(Diagnostic::error(format!("{} is too large", kind)).with_span(span));
+        }
+
+        Ok(struct_ref.ty())
+    }
+
+    // enum の後に続く タグ { 定数の宣言 } を読む
+    // enumの型はintとして扱うので、タグは読み飛ばすだけでよい
+    fn enum_decl(&mut self, var_env: &mut VarEnvironment) -> Result<Ty, Diagnostic> {
+        let tag = self.token_list.try_consume(&TokenKind::Ident);
+        if tag.is_some()
+            && !matches!(self.token_list.peek(), Some(next_token) if next_token.kind == TokenKind::LBrace)
+        {
+            return Ok(Ty::Int);
+        }
+
+        self.token_list.expect_kind(&TokenKind::LBrace)?;
+        // 値を省略した定数は、直前の定数の値に1を足した値になる
+        // intの最大値の次は表せないので、その値が必要になった時だけエラーにする
+        let mut next_value = Some(0);
+        loop {
+            let name_tok = self.token_list.expect_kind(&TokenKind::Ident)?;
+            let name_span = name_tok.span();
+            let name = name_tok.str.unwrap();
+            let value = if self.token_list.try_consume(&TokenKind::Assign).is_some() {
+                self.const_expr(var_env)?
             } else {
-                paren_consumed = true;
+                next_value.ok_or_else(|| {
+                    Diagnostic::error(format!(
+                        "Enumerator value for '{}' is not representable as int",
+                        name
+                    ))
+                    .with_span(name_span)
+                })?
+            };
+            Self::check_not_redeclared(&name, name_span, var_env)?;
+            var_env.add_enum_constant(&name, value);
+            next_value = value.checked_add(1);
+
+            if self.token_list.try_consume(&TokenKind::RBrace).is_some() {
+                break;
+            }
+            self.token_list.expect_kind(&TokenKind::Comma)?;
+            // 最後の定数の後の , は省略できる
+            if self.token_list.try_consume(&TokenKind::RBrace).is_some() {
                 break;
             }
         }
-        if !paren_consumed {
-            self.token_list.expect_kind(&TokenKind::RParen);
+
+        Ok(Ty::Int)
+    }
+
+    // 中身の分からない構造体の変数やメンバは作れない
+    fn check_complete_type(ty: &Ty, span: Span) -> Result<(), Diagnostic> {
+        if ty.is_complete() {
+            Ok(())
+        } else {
+            Err(Diagnostic::error(format!("Variable has incomplete type {:?}", ty)).with_span(span))
         }
+    }
 
-        args
+    // 同じスコープで宣言した変数や定数と同じ名前は宣言できない
+    fn check_not_redeclared(
+        name: &str,
+        span: Span,
+        var_env: &VarEnvironment,
+    ) -> Result<(), Diagnostic> {
+        if var_env.is_declared_in_current_scope(name) {
+            Err(Diagnostic::error(format!("Redefinition of '{}'", name)).with_span(span))
+        } else {
+            Ok(())
+        }
     }
 
-    fn fundef_body(&mut self, var_env: &mut VarEnvironment) -> Vec<Node> {
-        self.token_list.expect_kind(&TokenKind::LBrace);
+    // 関数名の後に続く (仮引数) と本体を読む。本体のないプロトタイプならNoneを返す
+    fn fundef(
+        &mut self,
+        name: String,
+        name_span: Span,
+        return_ty: Ty,
+        var_env: &mut VarEnvironment,
+    ) -> Result<Option<Node>, Diagnostic> {
+        // 構造体や配列を値で返す関数はサポートしていない
+        if return_ty.is_struct_or_union() || matches!(return_ty, Ty::Array(..)) {
+            return Err(Diagnostic::error(format!(
+                "Function returning {:?} is not supported",
+                return_ty
+            ))
+            .with_span(name_span));
+        }
+        let is_prototyped =
+            !matches!(self.token_list.peek_nth(1), Some(token) if token.kind == TokenKind::RParen);
+        let (params, is_variadic) = self.fundef_params(var_env)?;
+        let is_defined = !matches!(self.token_list.peek(), Some(next_token) if next_token.kind == TokenKind::Semicolon);
+        let function_info = FunctionInfo {
+            name: name.clone(),
+            return_ty,
+            param_tys: params.iter().map(|param| param.ty.clone()).collect(),
+            is_variadic,
+            is_prototyped,
+            is_defined,
+            span: name_span,
+        };
+        self.declare_function(function_info.clone(), name_span, var_env)?;
+        if !is_defined {
+            self.token_list.expect_kind(&TokenKind::Semicolon)?;
+            return Ok(None);
+        }
+
+        // assign offsets to local variables
+        // スタックのトップには、FPとLRが保存されているので、-16以降が変数領域
+        let mut args = vec![];
+        for param in params {
+            let param_name = match param.name {
+                Some(param_name) => param_name,
+                None => {
+                    return Err(Diagnostic::error("Parameter name omitted").with_span(param.span));
+                }
+            };
+            Self::check_not_redeclared(&param_name, param.span, var_env)?;
+            let arg_var_info = var_env.add_local_var(&param_name, param.ty);
+            args.push(Node::new(
+                Ast::LocalVar {
+                    name: param_name,
+                    offset: arg_var_info.offset,
+                },
+                Some(arg_var_info.ty),
+            ));
+        }
+        // 可変長引数の関数は、va_argで読めるように引数のレジスタを保存しておく
+        let va_area = if is_variadic {
+            let va_area_size = self.target.va_area_size();
+            if va_area_size == 0 {
+                Some(0)
+            } else {
+                let va_area_ty = Ty::Array(Box::new(Ty::Ptr(Box::new(Ty::Void))), va_area_size / 8);
+                Some(var_env.add_local_var("__va_area__", va_area_ty).offset)
+            }
+        } else {
+            None
+        };
+        self.current_function = Some(function_info);
+        self.va_area = va_area;
+        let body = self.fundef_body(var_env);
+        self.current_function = None;
+        self.va_area = None;
+        let body = body?;
+        let stack_size = var_env.stack_size();
+
+        Ok(Some(Node::new(
+            Ast::Fundef {
+                name,
+                args,
+                body,
+                stack_size,
+                va_area,
+            },
+            None,
+        )))
+    }
+
+    // ( 型 名前, ... ) を読む。最後が ... なら可変長引数の関数
+    fn fundef_params(
+        &mut self,
+        var_env: &mut VarEnvironment,
+    ) -> Result<(Vec<Param>, bool), Diagnostic> {
+        let mut params = vec![];
+        let mut is_variadic = false;
+        self.token_list.expect_kind(&TokenKind::LParen)?;
+        if self.token_list.try_consume(&TokenKind::RParen).is_some() {
+            return Ok((params, is_variadic));
+        }
+        // (void) は引数のない関数
+        if matches!(self.token_list.peek(), Some(token) if token.kind == TokenKind::Void)
+            && matches!(self.token_list.peek_nth(1), Some(token) if token.kind == TokenKind::RParen)
+        {
+            self.token_list.next();
+            self.token_list.next();
+            return Ok((params, is_variadic));
+        }
+        loop {
+            if self.token_list.try_consume(&TokenKind::Ellipsis).is_some() {
+                is_variadic = true;
+                self.token_list.expect_kind(&TokenKind::RParen)?;
+                break;
+            }
+            let span = self.token_list.current_span();
+            let mut ty = match self.declspec(var_env)? {
+                Some(ty) => ty,
+                None => {
+                    return Err(
+                        Diagnostic::error("Unexpected Token, Expected type name").with_span(span)
+                    );
+                }
+            };
+            ty = self.type_prefix(&ty);
+            let name_tok = self.token_list.try_consume(&TokenKind::Ident);
+            let name_span = name_tok.as_ref().map_or(span, |name_tok| name_tok.span());
+            let name = name_tok.map(|name_tok| name_tok.str.unwrap());
+            // 配列の引数はポインタとして受け取る。int a[] のように先頭の要素数は省略できる
+            if matches!(self.token_list.peek(), Some(token) if token.kind == TokenKind::LBracket)
+                && matches!(self.token_list.peek_nth(1), Some(token) if token.kind == TokenKind::RBracket)
+            {
+                self.token_list.next();
+                self.token_list.next();
+                ty = Ty::Ptr(Box::new(self.type_suffix(&ty, name_span, var_env)?));
+            } else {
+                ty = self.type_suffix(&ty, name_span, var_env)?;
+                if let Ty::Array(base_ty, _) = ty {
+                    ty = Ty::Ptr(base_ty);
+                }
+            }
+            // 構造体はレジスタに収まらないので、値では渡せない
+            if ty.is_struct_or_union() {
+                return Err(Diagnostic::error(format!(
+                    "Parameter of type {:?} is not supported",
+                    ty
+                ))
+                .with_span(span));
+            }
+            if ty == Ty::Void {
+                return Err(Diagnostic::error("Parameter has incomplete type Void").with_span(span));
+            }
+            params.push(Param { name, span, ty });
+            if self.token_list.try_consume(&TokenKind::RParen).is_some() {
+                break;
+            }
+            self.token_list.expect_kind(&TokenKind::Comma)?;
+        }
+
+        Ok((params, is_variadic))
+    }
+
+    // 関数を関数の一覧に登録する。同じ名前の関数が宣言済みなら、型が一致するか確かめる
+    // () で宣言した関数とは、戻り値の型だけを比べる
+    fn declare_function(
+        &mut self,
+        mut function_info: FunctionInfo,
+        name_span: Span,
+        var_env: &mut VarEnvironment,
+    ) -> Result<(), Diagnostic> {
+        if let Some(declared) = var_env.resolve_function(&function_info.name) {
+            if declared.is_defined && function_info.is_defined {
+                return Err(
+                    Diagnostic::error(format!("Redefinition of {}", function_info.name))
+                        .with_span(name_span),
+                );
+            }
+            let params_conflict = declared.is_prototyped
+                && function_info.is_prototyped
+                && (declared.param_tys != function_info.param_tys
+                    || declared.is_variadic != function_info.is_variadic);
+            if declared.return_ty != function_info.return_ty || params_conflict {
+                return Err(Diagnostic::error(format!(
+                    "Conflicting types for {}",
+                    function_info.name
+                ))
+                .with_span(name_span)
+                .with_note(self.declared_at(&function_info.name, declared.span)));
+            }
+            if declared.is_defined {
+                return Ok(());
+            }
+            // 引数の分かる宣言を残して、呼び出しの引数を確かめられるようにする
+            if declared.is_prototyped && !function_info.is_prototyped {
+                function_info.param_tys = declared.param_tys;
+                function_info.is_variadic = declared.is_variadic;
+                function_info.is_prototyped = true;
+            }
+        }
+        var_env.add_function(function_info);
+
+        Ok(())
+    }
+
+    fn fundef_body(&mut self, var_env: &mut VarEnvironment) -> Result<Vec<Node>, Diagnostic> {
+        self.token_list.expect_kind(&TokenKind::LBrace)?;
+        // 関数の本体は引数と同じスコープで読む
+        self.block_items(var_env)
+    }
+
+    // { の後の文を } まで読む。ブロックの中で宣言した名前は } までしか使えない
+    fn compound_stmt(&mut self, var_env: &mut VarEnvironment) -> Result<Vec<Node>, Diagnostic> {
+        var_env.enter_block();
+        let stmts = self.block_items(var_env);
+        var_env.leave_block();
+
+        stmts
+    }
+
+    fn block_items(&mut self, var_env: &mut VarEnvironment) -> Result<Vec<Node>, Diagnostic> {
         let mut stmts = vec![];
         while self.token_list.try_consume(&TokenKind::RBrace).is_none() {
-            stmts.push(self.stmt(var_env));
+            if self.token_list.at_end() {
+                // } が閉じられないまま入力が終わった
+                self.token_list.expect_kind(&TokenKind::RBrace)?;
+            }
+            match self.stmt(var_env) {
+                Ok(stmt) => stmts.push(stmt),
+                Err(diagnostic) => {
+                    self.recover(diagnostic)?;
+                    self.synchronize_stmt();
+                }
+            }
         }
 
-        stmts
+        Ok(stmts)
     }
 
-    fn stmt(&mut self, var_env: &mut VarEnvironment) -> Node {
-        if self.token_list.try_consume(&TokenKind::Int).is_some() {
-            let mut ty = Ty::Int;
-            ty = self.type_prefix(&ty);
-            let ident_name = self.token_list.expect_kind(&TokenKind::Ident).str.unwrap();
-            ty = self.type_suffix(&ty);
-            self.token_list.expect_kind(&TokenKind::Semicolon);
-            var_env.add_local_var(&ident_name, ty.clone());
+    // ループの本体ではbreakとcontinueが使える
+    fn loop_body(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        self.loop_depth += 1;
+        let body = self.stmt(var_env);
+        self.loop_depth -= 1;
 
-            Node::new(Ast::LocalVarDef(ident_name, ty), None)
-        } else if self.token_list.try_consume(&TokenKind::Char).is_some() {
-            let mut ty = Ty::Char;
-            ty = self.type_prefix(&ty);
-            let ident_name = self.token_list.expect_kind(&TokenKind::Ident).str.unwrap();
-            ty = self.type_suffix(&ty);
-            self.token_list.expect_kind(&TokenKind::Semicolon);
-            var_env.add_local_var(&ident_name, ty.clone());
+        body
+    }
 
-            Node::new(Ast::LocalVarDef(ident_name, ty), None)
-        } else if self.token_list.try_consume(&TokenKind::Return).is_some() {
-            let return_value = self.expr(var_env);
-            self.token_list.expect_kind(&TokenKind::Semicolon);
+    fn stmt(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        if let Some(return_tok) = self.token_list.try_consume(&TokenKind::Return) {
+            let return_ty = self.current_function.as_ref().unwrap().return_ty.clone();
+            if self.token_list.try_consume(&TokenKind::Semicolon).is_some() {
+                if return_ty != Ty::Void {
+                    return Err(Diagnostic::error(format!(
+                        "Non-void function should return a value of type {:?}",
+                        return_ty
+                    ))
+                    .with_span(return_tok.span()));
+                }
+                return Ok(Node::new(Ast::Return(None), None));
+            }
+            let value_span = self.token_list.current_span();
+            let mut return_value = self.expr(var_env)?;
+            self.token_list.expect_kind(&TokenKind::Semicolon)?;
+            if return_ty == Ty::Void {
+                return Err(Diagnostic::error("Void function should not return a value")
+                    .with_span(value_span));
+            }
+            // 戻り値は宣言された型に変換する
+            if !Self::is_assignment_compatible(&return_value, &return_ty) {
+                return Err(Diagnostic::error(format!(
+                    "Returning {:?} from a function with incompatible result type {:?}",
+                    return_value.ty.unwrap(),
+                    return_ty
+                ))
+                .with_span(value_span));
+            }
+            return_value = Self::new_cast(return_value, return_ty);
 
-            Node::new(Ast::Return(Box::new(return_value)), None)
+            Ok(Node::new(Ast::Return(Some(Box::new(return_value))), None))
         } else if self.token_list.try_consume(&TokenKind::If).is_some() {
-            self.token_list.expect_kind(&TokenKind::LParen);
-            let condition = self.expr(var_env);
-            self.token_list.expect_kind(&TokenKind::RParen);
-            let then_body = self.stmt(var_env);
+            self.token_list.expect_kind(&TokenKind::LParen)?;
+            let condition = self.condition(var_env)?;
+            self.token_list.expect_kind(&TokenKind::RParen)?;
+            let then_body = self.stmt(var_env)?;
 
             if self.token_list.try_consume(&TokenKind::Else).is_some() {
-                let else_body = self.stmt(var_env);
+                let else_body = self.stmt(var_env)?;
 
-                return Node::new(
+                return Ok(Node::new(
                     Ast::If(
                         Box::new(condition),
                         Box::new(then_body),
                         Some(Box::new(else_body)),
                     ),
                     None,
-                );
+                ));
             }
 
-            Node::new(
+            Ok(Node::new(
                 Ast::If(Box::new(condition), Box::new(then_body), None),
                 None,
-            )
+            ))
         } else if self.token_list.try_consume(&TokenKind::While).is_some() {
-            self.token_list.expect_kind(&TokenKind::LParen);
-            let condition = self.expr(var_env);
-            self.token_list.expect_kind(&TokenKind::RParen);
-            let body = self.stmt(var_env);
+            self.token_list.expect_kind(&TokenKind::LParen)?;
+            let condition = self.condition(var_env)?;
+            self.token_list.expect_kind(&TokenKind::RParen)?;
+            let body = self.loop_body(var_env)?;
+
+            Ok(Node::new(
+                Ast::While(Box::new(condition), Box::new(body)),
+                None,
+            ))
+        } else if self.token_list.try_consume(&TokenKind::Do).is_some() {
+            let body = self.loop_body(var_env)?;
+            self.token_list.expect_kind(&TokenKind::While)?;
+            self.token_list.expect_kind(&TokenKind::LParen)?;
+            let condition = self.condition(var_env)?;
+            self.token_list.expect_kind(&TokenKind::RParen)?;
+            self.token_list.expect_kind(&TokenKind::Semicolon)?;
 
-            Node::new(Ast::While(Box::new(condition), Box::new(body)), None)
+            Ok(Node::new(
+                Ast::DoWhile(Box::new(body), Box::new(condition)),
+                None,
+            ))
         } else if self.token_list.try_consume(&TokenKind::For).is_some() {
             // forの後には、for (初期化; 条件; 更新) 本体
             // ただし、初期化, 条件, 更新はどれも省略可能
-            self.token_list.expect_kind(&TokenKind::LParen);
+            self.token_list.expect_kind(&TokenKind::LParen)?;
             let init = if self.token_list.try_consume(&TokenKind::Semicolon).is_some() {
                 None
             } else {
-                let node = self.expr(var_env);
-                self.token_list.expect_kind(&TokenKind::Semicolon);
+                let node = self.expr(var_env)?;
+                self.token_list.expect_kind(&TokenKind::Semicolon)?;
 
                 Some(Box::new(node))
             };
             let check = if self.token_list.try_consume(&TokenKind::Semicolon).is_some() {
                 None
             } else {
-                let node = self.expr(var_env);
-                self.token_list.expect_kind(&TokenKind::Semicolon);
+                let node = self.condition(var_env)?;
+                self.token_list.expect_kind(&TokenKind::Semicolon)?;
 
                 Some(Box::new(node))
             };
             let update = if self.token_list.try_consume(&TokenKind::RParen).is_some() {
                 None
             } else {
-                let node = self.expr(var_env);
-                self.token_list.expect_kind(&TokenKind::RParen);
+                let node = self.expr(var_env)?;
+                self.token_list.expect_kind(&TokenKind::RParen)?;
 
                 Some(Box::new(node))
             };
 
+            let body = self.loop_body(var_env)?;
+            Ok(Node::new(
+                Ast::For(init, check, update, Box::new(body)),
+                None,
+            ))
+        } else if self.token_list.try_consume(&TokenKind::Switch).is_some() {
+            self.token_list.expect_kind(&TokenKind::LParen)?;
+            let condition_span = self.token_list.current_span();
+            let condition = self.expr(var_env)?;
+            self.token_list.expect_kind(&TokenKind::RParen)?;
+            let condition_ty = condition.ty.clone().unwrap();
+            if !Self::is_integer(&condition_ty) {
+                return Err(Diagnostic::error(format!(
+                    "Statement requires expression of integer type ({:?} invalid)",
+                    condition_ty
+                ))
+                .with_span(condition_span));
+            }
+            // switchの中のswitchは、外側のcaseとは別に数える
+            let outer_cases = self.switch_cases.replace(SwitchCases::default());
             let body = self.stmt(var_env);
-            Node::new(Ast::For(init, check, update, Box::new(body)), None)
-        } else if self.token_list.try_consume(&TokenKind::LBrace).is_some() {
-            let mut stmts = vec![];
-            while self.token_list.try_consume(&TokenKind::RBrace).is_none() {
-                stmts.push(self.stmt(var_env));
+            let cases = std::mem::replace(&mut self.switch_cases, outer_cases).unwrap();
+            let body = body?;
+
+            Ok(Node::new(
+                Ast::Switch {
+                    condition: Box::new(condition),
+                    body: Box::new(body),
+                    case_values: cases.values,
+                    has_default: cases.has_default,
+                },
+                None,
+            ))
+        } else if let Some(case_tok) = self.token_list.try_consume(&TokenKind::Case) {
+            if self.switch_cases.is_none() {
+                return Err(
+                    Diagnostic::error("'case' statement not in switch statement")
+                        .with_span(case_tok.span()),
+                );
             }
+            let value_span = self.token_list.current_span();
+            let value = self.const_expr(var_env)?;
+            self.token_list.expect_kind(&TokenKind::Colon)?;
+            let cases = self.switch_cases.as_mut().unwrap();
+            if cases.values.contains(&value) {
+                return Err(Diagnostic::error(format!("Duplicate case value {}", value))
+                    .with_span(value_span));
+            }
+            let index = cases.values.len();
+            cases.values.push(value);
+            let body = self.stmt(var_env)?;
 
-            Node::new(Ast::Block(stmts), None)
-        } else if let Some(lvar) = self.local_var(var_env) {
-            lvar
+            Ok(Node::new(
+                Ast::Case {
+                    index,
+                    body: Box::new(body),
+                },
+                None,
+            ))
+        } else if let Some(default_tok) = self.token_list.try_consume(&TokenKind::Default) {
+            self.token_list.expect_kind(&TokenKind::Colon)?;
+            let cases = match self.switch_cases.as_mut() {
+                Some(cases) => cases,
+                None => {
+                    return Err(
+                        Diagnostic::error("'default' statement not in switch statement")
+                            .with_span(default_tok.span()),
+                    );
+                }
+            };
+            if cases.has_default {
+                return Err(Diagnostic::error("Multiple default labels in one switch")
+                    .with_span(default_tok.span()));
+            }
+            cases.has_default = true;
+            let body = self.stmt(var_env)?;
+
+            Ok(Node::new(Ast::Default(Box::new(body)), None))
+        } else if let Some(break_tok) = self.token_list.try_consume(&TokenKind::Break) {
+            if self.loop_depth == 0 && self.switch_cases.is_none() {
+                return Err(
+                    Diagnostic::error("'break' statement not in loop or switch statement")
+                        .with_span(break_tok.span()),
+                );
+            }
+            self.token_list.expect_kind(&TokenKind::Semicolon)?;
+
+            Ok(Node::new(Ast::Break, None))
+        } else if let Some(continue_tok) = self.token_list.try_consume(&TokenKind::Continue) {
+            if self.loop_depth == 0 {
+                return Err(
+                    Diagnostic::error("'continue' statement not in loop statement")
+                        .with_span(continue_tok.span()),
+                );
+            }
+            self.token_list.expect_kind(&TokenKind::Semicolon)?;
+
+            Ok(Node::new(Ast::Continue, None))
+        } else if self.token_list.try_consume(&TokenKind::LBrace).is_some() {
+            let stmts = self.compound_stmt(var_env)?;
+
+            Ok(Node::new(Ast::Block(stmts), None))
+        } else if let Some(lvar) = self.local_var(var_env)? {
+            Ok(lvar)
         } else {
-            let expr = self.expr(var_env);
-            self.token_list.expect_kind(&TokenKind::Semicolon);
+            let expr = self.expr(var_env)?;
+            self.token_list.expect_kind(&TokenKind::Semicolon)?;
 
-            expr
+            Ok(expr)
         }
     }
 
-    fn local_var(&mut self, var_env: &mut VarEnvironment) -> Option<Node> {
-        let mut ty;
-        if self.token_list.try_consume(&TokenKind::Int).is_some() {
-            ty = Ty::Int;
-        } else if self.token_list.try_consume(&TokenKind::Char).is_some() {
-            ty = Ty::Char;
-        } else {
+    // if や while の条件を読む。条件は整数かポインタでなければならない
+    fn condition(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let span = self.token_list.current_span();
+        let node = self.expr(var_env)?;
+        Self::check_not_void(&node, span)?;
+        Self::check_scalar_condition(&node, span)?;
+
+        Ok(node)
+    }
+
+    fn check_scalar_condition(condition: &Node, span: Span) -> Result<(), Diagnostic> {
+        let condition_ty = condition.ty.as_ref().unwrap();
+        if condition_ty.is_struct_or_union() || *condition_ty == Ty::Void {
+            return Err(Diagnostic::error(format!(
+                "Used type {:?} where arithmetic or pointer type is required",
+                condition_ty
+            ))
+            .with_span(span));
+        }
+
+        Ok(())
+    }
+
+    fn local_var(&mut self, var_env: &mut VarEnvironment) -> Result<Option<Node>, Diagnostic> {
+        if self.token_list.try_consume(&TokenKind::Typedef).is_some() {
+            self.typedef(var_env)?;
+            return Ok(Some(Node::new(Ast::Block(vec![]), None)));
+        }
+        let mut ty = match self.declspec(var_env)? {
+            Some(ty) => ty,
             // Local var should start from type name;
-            return None;
+            None => return Ok(None),
+        };
+        if self.token_list.try_consume(&TokenKind::Semicolon).is_some() {
+            // 型の宣言だけなので何もしない
+            return Ok(Some(Node::new(Ast::Block(vec![]), None)));
         }
 
         ty = self.type_prefix(&ty);
-        let ident_name = self.token_list.expect_kind(&TokenKind::Ident).str.unwrap();
-        ty = self.type_suffix(&ty);
-        self.token_list.expect_kind(&TokenKind::Semicolon);
+        let ident_tok = self.token_list.expect_kind(&TokenKind::Ident)?;
+        let ident_span = ident_tok.span();
+        let ident_name = ident_tok.str.unwrap();
+        ty = self.type_suffix(&ty, ident_span, var_env)?;
+        Self::check_complete_type(&ty, ident_span)?;
+        if !var_env.can_add_local_var(&ty) {
+            return Err(Diagnostic::error("Stack frame is too large").with_span(ident_span));
+        }
+        Self::check_not_redeclared(&ident_name, ident_span, var_env)?;
+        self.token_list.expect_kind(&TokenKind::Semicolon)?;
         var_env.add_local_var(&ident_name, ty.clone());
 
-        Some(Node::new(Ast::LocalVarDef(ident_name, ty), None))
+        Ok(Some(Node::new(Ast::LocalVarDef(ident_name, ty), None)))
     }
 
-    fn expr(&mut self, var_env: &mut VarEnvironment) -> Node {
-        self.assign(var_env)
+    // コンパイル時に値が決まる式を読んで、その値を返す
+    fn const_expr(&mut self, var_env: &mut VarEnvironment) -> Result<i32, Diagnostic> {
+        let span = self.token_list.current_span();
+        let node = self.conditional(var_env)?;
+        Self::eval(&node)
+            .ok_or_else(|| Diagnostic::error("Expected constant expression").with_span(span))
     }
 
-    fn assign(&mut self, var_env: &mut VarEnvironment) -> Node {
-        let mut node = self.equality(var_env);
+    fn eval(node: &Node) -> Option<i32> {
+        match node.ast {
+            Ast::Num(n) => Some(n),
+            Ast::BinOp(ref op, ref lhs, ref rhs) => {
+                let lhs = Self::eval(lhs)?;
+                let rhs = Self::eval(rhs)?;
+                match op {
+                    BinOpType::Add => Some(lhs.wrapping_add(rhs)),
+                    BinOpType::Sub => Some(lhs.wrapping_sub(rhs)),
+                    BinOpType::Mul => Some(lhs.wrapping_mul(rhs)),
+                    BinOpType::Div => lhs.checked_div(rhs),
+                    BinOpType::Mod => lhs.checked_rem(rhs),
+                    BinOpType::Equal => Some((lhs == rhs) as i32),
+                    BinOpType::NotEqual => Some((lhs != rhs) as i32),
+                    BinOpType::LessThan => Some((lhs < rhs) as i32),
+                    BinOpType::LessThanOrEqual => Some((lhs <= rhs) as i32),
+                    BinOpType::BitAnd => Some(lhs & rhs),
+                    BinOpType::BitOr => Some(lhs | rhs),
+                    BinOpType::BitXor => Some(lhs ^ rhs),
+                    BinOpType::ShiftLeft => lhs.checked_shl(rhs as u32),
+                    BinOpType::ShiftRight => lhs.checked_shr(rhs as u32),
+                }
+            }
+            Ast::BitNot(ref operand) => Some(!Self::eval(operand)?),
+            Ast::Conditional(ref condition, ref then_value, ref else_value) => {
+                if Self::eval(condition)? != 0 {
+                    Self::eval(then_value)
+                } else {
+                    Self::eval(else_value)
+                }
+            }
+            Ast::Cast(ref base) => {
+                let value = Self::eval(base)?;
+                match node.ty {
+                    Some(Ty::Char) => Some(value as i8 as i32),
+                    _ => Some(value),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let mut node = self.assign(var_env)?;
+
+        while self.token_list.try_consume(&TokenKind::Comma).is_some() {
+            let rhs = self.assign(var_env)?;
+            let rhs_ty = rhs.ty.clone();
+            node = Node::new(Ast::Comma(Box::new(node), Box::new(rhs)), rhs_ty);
+        }
+
+        Ok(node)
+    }
+
+    fn assign(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let lhs_span = self.token_list.current_span();
+        let mut node = self.conditional(var_env)?;
         if self.token_list.try_consume(&TokenKind::Assign).is_some() {
             let lhs = node;
-            if let Some(Ty::Array(..)) = lhs.ty {
-                panic!("{:?} is not a lvalue", lhs)
-            }
+            Self::check_assignable(&lhs, lhs_span)?;
             let lhs_ty = lhs.ty.clone();
-            let rhs = self.assign(var_env);
+            let rhs_span = self.token_list.current_span();
+            let rhs = self.assign(var_env)?;
+            Self::check_not_void(&rhs, rhs_span)?;
+            // 構造体や共用体は同じ型の値からしか代入できず、他の型にも代入できない
+            let is_struct_or_union =
+                |ty: &Option<Ty>| matches!(ty, Some(ty) if ty.is_struct_or_union());
+            if (is_struct_or_union(&lhs_ty) || is_struct_or_union(&rhs.ty)) && rhs.ty != lhs_ty {
+                return Err(Diagnostic::error(format!(
+                    "Cannot assign {:?} to {:?}",
+                    rhs.ty.unwrap(),
+                    lhs_ty.unwrap()
+                ))
+                .with_span(rhs_span));
+            }
             node = Node::new(Ast::Assign(Box::new(lhs), Box::new(rhs)), lhs_ty);
+        } else if let Some((op, op_span)) = self.try_consume_compound_assign() {
+            Self::check_assignable(&node, lhs_span)?;
+            let rhs = self.assign(var_env)?;
+            node = Self::new_compound_assign(op, node, rhs, op_span)?;
+        }
+
+        Ok(node)
+    }
+
+    // 複合代入演算子を読んで、対応する二項演算を返す
+    fn try_consume_compound_assign(&mut self) -> Option<(BinOpType, Span)> {
+        let ops = [
+            (TokenKind::PlusAssign, BinOpType::Add),
+            (TokenKind::MinusAssign, BinOpType::Sub),
+            (TokenKind::StarAssign, BinOpType::Mul),
+            (TokenKind::DivAssign, BinOpType::Div),
+            (TokenKind::ModAssign, BinOpType::Mod),
+            (TokenKind::AmpersandAssign, BinOpType::BitAnd),
+            (TokenKind::PipeAssign, BinOpType::BitOr),
+            (TokenKind::CaretAssign, BinOpType::BitXor),
+            (TokenKind::ShiftLeftAssign, BinOpType::ShiftLeft),
+            (TokenKind::ShiftRightAssign, BinOpType::ShiftRight),
+        ];
+        ops.into_iter().find_map(|(kind, op)| {
+            self.token_list
+                .try_consume(&kind)
+                .map(|token| (op, token.span()))
+        })
+    }
+
+    fn new_compound_assign(
+        op: BinOpType,
+        lhs: Node,
+        rhs: Node,
+        op_span: Span,
+    ) -> Result<Node, Diagnostic> {
+        let lhs_ty = lhs.ty.clone().unwrap();
+        let rhs_ty = rhs.ty.as_ref().unwrap();
+        // ポインタには整数を足し引きすることだけができる
+        let is_pointer_arithmetic =
+            matches!(op, BinOpType::Add | BinOpType::Sub) && lhs_ty.is_reference_type();
+        if !(Self::is_integer(&lhs_ty) || is_pointer_arithmetic) || !Self::is_integer(rhs_ty) {
+            return Err(Diagnostic::error(format!(
+                "Invalid operands to binary expression ({:?} and {:?})",
+                lhs_ty, rhs_ty
+            ))
+            .with_span(op_span));
+        }
+        let rhs = if is_pointer_arithmetic {
+            Self::scale_offset(rhs, &lhs_ty)
+        } else {
+            rhs
+        };
+
+        Ok(Node::new(
+            Ast::CompoundAssign(op, Box::new(lhs), Box::new(rhs)),
+            Some(lhs_ty),
+        ))
+    }
+
+    // x++ は (x += 1) - 1 として扱う
+    fn new_postfix_increment(op: BinOpType, node: Node, op_span: Span) -> Result<Node, Diagnostic> {
+        let ty = node.ty.clone().unwrap();
+        let one = || Node::new(Ast::Num(1), Some(Ty::Int));
+        let is_increment = op == BinOpType::Add;
+        let updated = Self::new_compound_assign(op, node, one(), op_span)?;
+        let previous = if is_increment {
+            Self::new_sub(updated, one())
+        } else {
+            Self::new_add(updated, one())
+        };
+
+        Ok(Node::new(Ast::Cast(Box::new(previous)), Some(ty)))
+    }
+
+    fn conditional(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let condition = self.logical_or(var_env)?;
+        let question = match self.token_list.try_consume(&TokenKind::Question) {
+            Some(question) => question,
+            None => return Ok(condition),
+        };
+        Self::check_scalar_condition(&condition, question.span())?;
+        let then_value = self.expr(var_env)?;
+        self.token_list.expect_kind(&TokenKind::Colon)?;
+        let else_value = self.conditional(var_env)?;
+        let ty = Self::conditional_ty(&then_value, &else_value, question.span())?;
+
+        Ok(Node::new(
+            Ast::Conditional(
+                Box::new(condition),
+                Box::new(then_value),
+                Box::new(else_value),
+            ),
+            Some(ty),
+        ))
+    }
+
+    // 条件演算子の2つの値から結果の型を決める
+    fn conditional_ty(then_value: &Node, else_value: &Node, span: Span) -> Result<Ty, Diagnostic> {
+        // 配列はポインタとして扱う
+        let decay = |ty: &Ty| match ty {
+            Ty::Array(base_ty, _) => Ty::Ptr(base_ty.clone()),
+            ty => ty.clone(),
+        };
+        let then_ty = decay(then_value.ty.as_ref().unwrap());
+        let else_ty = decay(else_value.ty.as_ref().unwrap());
+        match (&then_ty, &else_ty) {
+            _ if Self::is_integer(&then_ty) && Self::is_integer(&else_ty) => Ok(Ty::Int),
+            _ if then_ty == else_ty => Ok(then_ty),
+            // 整数の0はヌルポインタとしてポインタに合わせる
+            (Ty::Ptr(_), _) if Self::is_null_pointer_constant(else_value) => Ok(then_ty),
+            (_, Ty::Ptr(_)) if Self::is_null_pointer_constant(then_value) => Ok(else_ty),
+            // void *と他のポインタはvoid *に合わせる
+            (Ty::Ptr(base_ty), Ty::Ptr(_)) | (Ty::Ptr(_), Ty::Ptr(base_ty))
+                if **base_ty == Ty::Void =>
+            {
+                Ok(Ty::Ptr(Box::new(Ty::Void)))
+            }
+            _ => Err(Diagnostic::error(format!(
+                "Incompatible operand types ({:?} and {:?})",
+                then_ty, else_ty
+            ))
+            .with_span(span)),
         }
+    }
 
-        node
+    // ヌルポインタ定数として扱うのは整数の0だけ
+    fn is_null_pointer_constant(node: &Node) -> bool {
+        matches!(node.ast, Ast::Num(0))
     }
 
-    fn equality(&mut self, var_env: &mut VarEnvironment) -> Node {
-        let mut node = self.relational(var_env);
+    fn logical_or(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let mut node = self.logical_and(var_env)?;
+
+        while let Some(op) = self.token_list.try_consume(&TokenKind::LogicalOr) {
+            let rhs = self.logical_and(var_env)?;
+            Self::check_arithmetic_operands(&node, &rhs, op.span())?;
+            node = Node::new(Ast::LogicalOr(Box::new(node), Box::new(rhs)), Some(Ty::Int));
+        }
+
+        Ok(node)
+    }
+
+    fn logical_and(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let mut node = self.bit_or(var_env)?;
+
+        while let Some(op) = self.token_list.try_consume(&TokenKind::LogicalAnd) {
+            let rhs = self.bit_or(var_env)?;
+            Self::check_arithmetic_operands(&node, &rhs, op.span())?;
+            node = Node::new(
+                Ast::LogicalAnd(Box::new(node), Box::new(rhs)),
+                Some(Ty::Int),
+            );
+        }
+
+        Ok(node)
+    }
+
+    fn bit_or(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let mut node = self.bit_xor(var_env)?;
+
+        while let Some(op) = self.token_list.try_consume(&TokenKind::Pipe) {
+            let rhs = self.bit_xor(var_env)?;
+            node = Self::new_integer_binop(BinOpType::BitOr, node, rhs, op.span())?;
+        }
+
+        Ok(node)
+    }
+
+    fn bit_xor(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let mut node = self.bit_and(var_env)?;
+
+        while let Some(op) = self.token_list.try_consume(&TokenKind::Caret) {
+            let rhs = self.bit_and(var_env)?;
+            node = Self::new_integer_binop(BinOpType::BitXor, node, rhs, op.span())?;
+        }
+
+        Ok(node)
+    }
+
+    fn bit_and(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let mut node = self.equality(var_env)?;
+
+        while let Some(op) = self.token_list.try_consume(&TokenKind::Ampersand) {
+            let rhs = self.equality(var_env)?;
+            node = Self::new_integer_binop(BinOpType::BitAnd, node, rhs, op.span())?;
+        }
+
+        Ok(node)
+    }
+
+    fn equality(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let mut node = self.relational(var_env)?;
 
         loop {
-            if self.token_list.try_consume(&TokenKind::Equal).is_some() {
+            if let Some(op) = self.token_list.try_consume(&TokenKind::Equal) {
                 let lhs = node;
-                let rhs = self.relational(var_env);
+                let rhs = self.relational(var_env)?;
+                Self::check_arithmetic_operands(&lhs, &rhs, op.span())?;
                 node = Node::new(
                     Ast::BinOp(BinOpType::Equal, Box::new(lhs), Box::new(rhs)),
                     Some(Ty::Int),
                 );
-            } else if self.token_list.try_consume(&TokenKind::NotEqual).is_some() {
+            } else if let Some(op) = self.token_list.try_consume(&TokenKind::NotEqual) {
                 let lhs = node;
-                let rhs = self.relational(var_env);
+                let rhs = self.relational(var_env)?;
+                Self::check_arithmetic_operands(&lhs, &rhs, op.span())?;
                 node = Node::new(
                     Ast::BinOp(BinOpType::NotEqual, Box::new(lhs), Box::new(rhs)),
                     Some(Ty::Int),
                 );
             } else {
-                return node;
+                return Ok(node);
             }
         }
     }
 
-    fn relational(&mut self, var_env: &mut VarEnvironment) -> Node {
-        let mut node = self.add(var_env);
+    fn relational(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let mut node = self.shift(var_env)?;
 
         loop {
-            if self.token_list.try_consume(&TokenKind::LessThan).is_some() {
+            if let Some(op) = self.token_list.try_consume(&TokenKind::LessThan) {
                 let lhs = node;
-                let rhs = self.add(var_env);
+                let rhs = self.shift(var_env)?;
+                Self::check_arithmetic_operands(&lhs, &rhs, op.span())?;
                 node = Node::new(
                     Ast::BinOp(BinOpType::LessThan, Box::new(lhs), Box::new(rhs)),
                     Some(Ty::Int),
                 );
-            } else if self
-                .token_list
-                .try_consume(&TokenKind::LessThanOrEqual)
-                .is_some()
-            {
+            } else if let Some(op) = self.token_list.try_consume(&TokenKind::LessThanOrEqual) {
                 let lhs = node;
-                let rhs = self.add(var_env);
+                let rhs = self.shift(var_env)?;
+                Self::check_arithmetic_operands(&lhs, &rhs, op.span())?;
                 node = Node::new(
                     Ast::BinOp(BinOpType::LessThanOrEqual, Box::new(lhs), Box::new(rhs)),
                     Some(Ty::Int),
                 );
-            } else if self
-                .token_list
-                .try_consume(&TokenKind::GreaterThan)
-                .is_some()
-            {
-                let lhs = self.add(var_env);
+            } else if let Some(op) = self.token_list.try_consume(&TokenKind::GreaterThan) {
+                let lhs = self.shift(var_env)?;
                 let rhs = node;
+                Self::check_arithmetic_operands(&lhs, &rhs, op.span())?;
                 node = Node::new(
                     Ast::BinOp(BinOpType::LessThan, Box::new(lhs), Box::new(rhs)),
                     Some(Ty::Int),
                 );
-            } else if self
-                .token_list
-                .try_consume(&TokenKind::GreaterThanOrEqual)
-                .is_some()
-            {
-                let lhs = self.add(var_env);
+            } else if let Some(op) = self.token_list.try_consume(&TokenKind::GreaterThanOrEqual) {
+                let lhs = self.shift(var_env)?;
                 let rhs = node;
+                Self::check_arithmetic_operands(&lhs, &rhs, op.span())?;
                 node = Node::new(
                     Ast::BinOp(BinOpType::LessThanOrEqual, Box::new(lhs), Box::new(rhs)),
                     Some(Ty::Int),
                 );
             } else {
-                return node;
+                return Ok(node);
             }
         }
     }
 
-    fn add(&mut self, var_env: &mut VarEnvironment) -> Node {
-        let mut node = self.mul(var_env);
+    fn shift(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let mut node = self.add(var_env)?;
 
         loop {
-            if self.token_list.try_consume(&TokenKind::Plus).is_some() {
-                let lhs = node;
-                let lhs_ty = lhs.ty.clone();
-                let mut rhs = self.mul(var_env);
-                if let Some(ref lhs_ty) = lhs_ty {
-                    if lhs_ty.is_reference_type() {
-                        rhs = Node::new(
-                            Ast::BinOp(
-                                BinOpType::Mul,
-                                Box::new(rhs),
-                                Box::new(Node::new(
-                                    Ast::Num(lhs_ty.clone().base_ty().size()),
-                                    Some(Ty::Int),
-                                )),
-                            ),
-                            Some(Ty::Int),
-                        );
-                        node = Node::new(
-                            Ast::BinOp(BinOpType::Add, Box::new(lhs), Box::new(rhs)),
-                            Some(lhs_ty.clone()),
-                        );
-                    } else {
-                        node = Node::new(
-                            Ast::BinOp(BinOpType::Add, Box::new(lhs), Box::new(rhs)),
-                            Some(lhs_ty.clone()),
-                        );
-                    }
-                } else {
-                    unreachable!();
-                }
-            } else if self.token_list.try_consume(&TokenKind::Minus).is_some() {
-                let lhs = node;
-                let lhs_ty = lhs.ty.clone();
-                let mut rhs = self.mul(var_env);
-                match &lhs_ty {
-                    Some(Ty::Int) => {
-                        node = Node::new(
-                            Ast::BinOp(BinOpType::Sub, Box::new(lhs), Box::new(rhs)),
-                            lhs_ty.clone(),
-                        );
-                    }
-                    //何かの値の参照をしている型は、参照先の型のサイズに応じてスケールする必要があるので欠け算のノードを挟んでおく
-                    Some(refrence_type) => {
-                        rhs = Node::new(
-                            Ast::BinOp(
-                                BinOpType::Mul,
-                                Box::new(rhs),
-                                Box::new(Node::new(
-                                    Ast::Num(refrence_type.base_ty().size()),
-                                    Some(Ty::Int),
-                                )),
-                            ),
-                            Some(Ty::Int),
-                        );
-                        node = Node::new(
-                            Ast::BinOp(BinOpType::Sub, Box::new(lhs), Box::new(rhs)),
-                            lhs_ty.clone(),
-                        );
-                    }
-                    None => unreachable!(),
-                }
+            if let Some(op) = self.token_list.try_consume(&TokenKind::ShiftLeft) {
+                let rhs = self.add(var_env)?;
+                node = Self::new_integer_binop(BinOpType::ShiftLeft, node, rhs, op.span())?;
+            } else if let Some(op) = self.token_list.try_consume(&TokenKind::ShiftRight) {
+                let rhs = self.add(var_env)?;
+                node = Self::new_integer_binop(BinOpType::ShiftRight, node, rhs, op.span())?;
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    fn add(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let mut node = self.mul(var_env)?;
+
+        loop {
+            if let Some(plus) = self.token_list.try_consume(&TokenKind::Plus) {
+                let rhs = self.mul(var_env)?;
+                Self::check_arithmetic_operands(&node, &rhs, plus.span())?;
+                node = Self::new_add(node, rhs);
+            } else if let Some(minus) = self.token_list.try_consume(&TokenKind::Minus) {
+                let rhs = self.mul(var_env)?;
+                Self::check_arithmetic_operands(&node, &rhs, minus.span())?;
+                node = Self::new_sub(node, rhs);
             } else {
-                return node;
+                return Ok(node);
             }
         }
     }
 
-    fn mul(&mut self, var_env: &mut VarEnvironment) -> Node {
-        let mut node = self.unary(var_env);
+    // 構造体や共用体の値とvoidの値は計算に使えない
+    fn check_arithmetic_operands(lhs: &Node, rhs: &Node, op_span: Span) -> Result<(), Diagnostic> {
+        let lhs_ty = lhs.ty.as_ref().unwrap();
+        let rhs_ty = rhs.ty.as_ref().unwrap();
+        let is_invalid = |ty: &Ty| ty.is_struct_or_union() || *ty == Ty::Void;
+        if is_invalid(lhs_ty) || is_invalid(rhs_ty) {
+            return Err(Diagnostic::error(format!(
+                "Invalid operands to binary expression ({:?} and {:?})",
+                lhs_ty, rhs_ty
+            ))
+            .with_span(op_span));
+        }
+
+        Ok(())
+    }
+
+    // voidの値は式の中で使えない
+    fn check_not_void(node: &Node, span: Span) -> Result<(), Diagnostic> {
+        if node.ty == Some(Ty::Void) {
+            return Err(
+                Diagnostic::error("Void value not ignored as it ought to be").with_span(span),
+            );
+        }
+
+        Ok(())
+    }
+
+    // ビット演算やシフトは整数同士でしかできない
+    fn new_integer_binop(
+        op: BinOpType,
+        lhs: Node,
+        rhs: Node,
+        op_span: Span,
+    ) -> Result<Node, Diagnostic> {
+        let lhs_ty = lhs.ty.as_ref().unwrap();
+        let rhs_ty = rhs.ty.as_ref().unwrap();
+        if !Self::is_integer(lhs_ty) || !Self::is_integer(rhs_ty) {
+            return Err(Diagnostic::error(format!(
+                "Invalid operands to binary expression ({:?} and {:?})",
+                lhs_ty, rhs_ty
+            ))
+            .with_span(op_span));
+        }
+
+        Ok(Node::new(
+            Ast::BinOp(op, Box::new(lhs), Box::new(rhs)),
+            Some(Ty::Int),
+        ))
+    }
+
+    fn is_integer(ty: &Ty) -> bool {
+        matches!(ty, Ty::Int | Ty::Char)
+    }
+
+    // va_start, va_arg, va_endに渡されたva_listのアドレスを求める
+    // 配列で表すva_listは、引数として受け取るとポインタになるので、その値がアドレスになる
+    fn va_list_address(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let ap_span = self.token_list.current_span();
+        let ap = self.assign(var_env)?;
+        let va_list_ty = self.target.va_list_ty();
+        let ap_ty = ap.ty.clone().unwrap();
+        if ap_ty == va_list_ty && Self::is_lvalue(&ap) {
+            return Ok(Node::new(
+                Ast::Addr(Box::new(ap)),
+                Some(Ty::Ptr(Box::new(ap_ty))),
+            ));
+        }
+        match va_list_ty {
+            Ty::Array(ref base_ty, _) if ap_ty == Ty::Ptr(base_ty.clone()) => Ok(ap),
+            _ => Err(
+                Diagnostic::error(format!("Expected va_list, but got {:?}", ap_ty))
+                    .with_span(ap_span),
+            ),
+        }
+    }
+
+    // 代入と同じ規則で、値をtyの型に変換できるか
+    fn is_assignment_compatible(node: &Node, ty: &Ty) -> bool {
+        // 配列はポインタとして扱う
+        let node_ty = match node.ty.as_ref().unwrap() {
+            Ty::Array(base_ty, _) => Ty::Ptr(base_ty.clone()),
+            node_ty => node_ty.clone(),
+        };
+        match (ty, &node_ty) {
+            _ if Self::is_integer(ty) && Self::is_integer(&node_ty) => true,
+            // ポインタ同士は指す先の型が同じか、どちらかがvoid *でなければならない
+            (Ty::Ptr(base_ty), Ty::Ptr(node_base_ty)) => {
+                base_ty == node_base_ty || **base_ty == Ty::Void || **node_base_ty == Ty::Void
+            }
+            // 整数の0はヌルポインタとしてポインタに変換できる
+            (Ty::Ptr(_), _) => Self::is_null_pointer_constant(node),
+            _ => *ty == node_ty,
+        }
+    }
+
+    // 型が異なる場合だけキャストのノードを挟む
+    fn new_cast(node: Node, ty: Ty) -> Node {
+        if node.ty.as_ref() == Some(&ty) {
+            node
+        } else {
+            Node::new(Ast::Cast(Box::new(node)), Some(ty))
+        }
+    }
+
+    // 何かの値の参照をしている型は、参照先の型のサイズに応じてスケールする必要があるので欠け算のノードを挟んでおく
+    fn new_add(lhs: Node, rhs: Node) -> Node {
+        let lhs_ty = lhs.ty.clone().unwrap();
+        let rhs = if lhs_ty.is_reference_type() {
+            Self::scale_offset(rhs, &lhs_ty)
+        } else {
+            rhs
+        };
+
+        Node::new(
+            Ast::BinOp(BinOpType::Add, Box::new(lhs), Box::new(rhs)),
+            Some(lhs_ty),
+        )
+    }
+
+    fn new_sub(lhs: Node, rhs: Node) -> Node {
+        let lhs_ty = lhs.ty.clone().unwrap();
+        let rhs = if lhs_ty.is_reference_type() {
+            Self::scale_offset(rhs, &lhs_ty)
+        } else {
+            rhs
+        };
+
+        Node::new(
+            Ast::BinOp(BinOpType::Sub, Box::new(lhs), Box::new(rhs)),
+            Some(lhs_ty),
+        )
+    }
+
+    //何かの値の参照をしている型は、参照先の型のサイズに応じてスケールする必要があるので欠け算のノードを挟んでおく
+    fn scale_offset(offset: Node, reference_ty: &Ty) -> Node {
+        Node::new(
+            Ast::BinOp(
+                BinOpType::Mul,
+                Box::new(offset),
+                Box::new(Node::new(
+                    Ast::Num(reference_ty.base_ty().size()),
+                    Some(Ty::Int),
+                )),
+            ),
+            Some(Ty::Int),
+        )
+    }
+
+    fn mul(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let mut node = self.unary(var_env)?;
         let mut node_ty = node.ty.clone();
 
         loop {
-            if self.token_list.try_consume(&TokenKind::Star).is_some() {
+            if let Some(op) = self.token_list.try_consume(&TokenKind::Star) {
                 let lhs = node;
-                let rhs = self.unary(var_env);
+                let rhs = self.unary(var_env)?;
+                Self::check_arithmetic_operands(&lhs, &rhs, op.span())?;
                 node = Node::new(
                     Ast::BinOp(BinOpType::Mul, Box::new(lhs), Box::new(rhs)),
                     node_ty,
                 );
                 node_ty = node.ty.clone();
-            } else if self.token_list.try_consume(&TokenKind::Div).is_some() {
+            } else if let Some(op) = self.token_list.try_consume(&TokenKind::Div) {
                 let lhs = node;
-                let rhs = self.unary(var_env);
+                let rhs = self.unary(var_env)?;
+                Self::check_arithmetic_operands(&lhs, &rhs, op.span())?;
                 node = Node::new(
                     Ast::BinOp(BinOpType::Div, Box::new(lhs), Box::new(rhs)),
                     node_ty,
                 );
                 node_ty = node.ty.clone();
+            } else if let Some(op) = self.token_list.try_consume(&TokenKind::Mod) {
+                let rhs = self.unary(var_env)?;
+                node = Self::new_integer_binop(BinOpType::Mod, node, rhs, op.span())?;
+                node_ty = node.ty.clone();
             } else {
-                return node;
+                return Ok(node);
             }
         }
     }
 
-    fn unary(&mut self, var_env: &mut VarEnvironment) -> Node {
+    fn unary(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
         if self.token_list.try_consume(&TokenKind::SizeOf).is_some() {
-            let node = self.unary(var_env);
+            if self.token_list.peek_nth(0).map(|token| token.kind) == Some(TokenKind::LParen)
+                && self.is_typename(1, var_env)
+            {
+                self.token_list.expect_kind(&TokenKind::LParen)?;
+                let ty_span = self.token_list.current_span();
+                let ty = self.type_name(var_env)?;
+                self.token_list.expect_kind(&TokenKind::RParen)?;
+                // 中身の分からない構造体の大きさは分からない
+                Self::check_complete_type(&ty, ty_span)?;
+                return Ok(Node::new(Ast::Num(ty.size()), Some(Ty::Int)));
+            }
+            let node_span = self.token_list.current_span();
+            let node = self.unary(var_env)?;
             let node_ty = node.ty.unwrap();
-            return Node::new(Ast::Num(node_ty.size()), Some(Ty::Int));
+            Self::check_complete_type(&node_ty, node_span)?;
+            return Ok(Node::new(Ast::Num(node_ty.size()), Some(Ty::Int)));
+        }
+        // ( の後に型名が続けばキャスト
+        if self.token_list.peek_nth(0).map(|token| token.kind) == Some(TokenKind::LParen)
+            && self.is_typename(1, var_env)
+        {
+            let lparen = self.token_list.expect_kind(&TokenKind::LParen)?;
+            let ty = self.type_name(var_env)?;
+            self.token_list.expect_kind(&TokenKind::RParen)?;
+            if ty.is_struct_or_union() || matches!(ty, Ty::Array(..)) {
+                return Err(
+                    Diagnostic::error(format!("Cannot cast to {:?}", ty)).with_span(lparen.span())
+                );
+            }
+            // voidへのキャスト以外では、値は整数かポインタでなければならない
+            let base_span = self.token_list.current_span();
+            let base = self.unary(var_env)?;
+            let base_ty = base.ty.clone().unwrap();
+            if ty != Ty::Void && (base_ty.is_struct_or_union() || base_ty == Ty::Void) {
+                return Err(Diagnostic::error(format!(
+                    "Operand of type {:?} where arithmetic or pointer type is required",
+                    base_ty
+                ))
+                .with_span(base_span));
+            }
+            return Ok(Node::new(Ast::Cast(Box::new(base)), Some(ty)));
         }
         if self.token_list.try_consume(&TokenKind::Plus).is_some() {
             // TODO: should check to_ptr_if_array?
-            return self.primary(var_env);
+            return self.postfix(var_env);
         }
-        if self.token_list.try_consume(&TokenKind::Minus).is_some() {
-            let rhs = self.primary(var_env);
-            return Node::new(
+        if let Some(op) = self.token_list.try_consume(&TokenKind::Minus) {
+            let rhs = self.postfix(var_env)?;
+            let zero = Node::new(Ast::Num(0), Some(Ty::Int));
+            Self::check_arithmetic_operands(&zero, &rhs, op.span())?;
+            return Ok(Node::new(
                 Ast::BinOp(
                     BinOpType::Sub,
                     Box::new(Node::new(Ast::Num(0), Some(Ty::Int))),
                     Box::new(rhs),
                 ),
                 Some(Ty::Int),
-            );
+            ));
+        }
+        // ++x は x += 1 として扱う
+        if let Some(op) = self.token_list.try_consume(&TokenKind::Increment) {
+            let operand_span = self.token_list.current_span();
+            let operand = self.unary(var_env)?;
+            Self::check_assignable(&operand, operand_span)?;
+            let one = Node::new(Ast::Num(1), Some(Ty::Int));
+            return Self::new_compound_assign(BinOpType::Add, operand, one, op.span());
+        }
+        if let Some(op) = self.token_list.try_consume(&TokenKind::Decrement) {
+            let operand_span = self.token_list.current_span();
+            let operand = self.unary(var_env)?;
+            Self::check_assignable(&operand, operand_span)?;
+            let one = Node::new(Ast::Num(1), Some(Ty::Int));
+            return Self::new_compound_assign(BinOpType::Sub, operand, one, op.span());
+        }
+        if let Some(op) = self.token_list.try_consume(&TokenKind::Tilde) {
+            let operand = self.unary(var_env)?;
+            let operand_ty = operand.ty.clone().unwrap();
+            if !Self::is_integer(&operand_ty) {
+                return Err(Diagnostic::error(format!(
+                    "Invalid argument type {:?} to unary expression",
+                    operand_ty
+                ))
+                .with_span(op.span()));
+            }
+            return Ok(Node::new(Ast::BitNot(Box::new(operand)), Some(Ty::Int)));
+        }
+        if let Some(op) = self.token_list.try_consume(&TokenKind::Not) {
+            let operand = self.unary(var_env)?;
+            let operand_ty = operand.ty.clone().unwrap();
+            if operand_ty.is_struct_or_union() || operand_ty == Ty::Void {
+                return Err(Diagnostic::error(format!(
+                    "Invalid argument type {:?} to unary expression",
+                    operand_ty
+                ))
+                .with_span(op.span()));
+            }
+            // !x は x == 0 と同じ
+            return Ok(Node::new(
+                Ast::BinOp(
+                    BinOpType::Equal,
+                    Box::new(operand),
+                    Box::new(Node::new(Ast::Num(0), Some(Ty::Int))),
+                ),
+                Some(Ty::Int),
+            ));
         }
         if self.token_list.try_consume(&TokenKind::Star).is_some() {
-            let base = self.unary(var_env);
-            let base_ty = base.ty.clone();
-            return Node::new(Ast::Deref(Box::new(base)), Some(base_ty.unwrap().base_ty()));
+            let base_span = self.token_list.current_span();
+            let base = self.unary(var_env)?;
+            let base_ty = base.ty.clone().unwrap();
+            if !base_ty.is_reference_type() {
+                return Err(Diagnostic::error(format!(
+                    "Cannot dereference non-pointer type {:?}",
+                    base_ty
+                ))
+                .with_span(base_span));
+            }
+            if base_ty.base_ty() == Ty::Void {
+                return Err(
+                    Diagnostic::error("Cannot dereference void pointer").with_span(base_span)
+                );
+            }
+            return Ok(Node::new(
+                Ast::Deref(Box::new(base)),
+                Some(base_ty.base_ty()),
+            ));
         }
         if self.token_list.try_consume(&TokenKind::Ampersand).is_some() {
-            let base = self.unary(var_env);
+            let base_span = self.token_list.current_span();
+            let base = self.unary(var_env)?;
+            if !Self::is_lvalue(&base) {
+                return Err(
+                    Diagnostic::error("Cannot take the address of a rvalue").with_span(base_span)
+                );
+            }
             let base_ty = base.ty.clone().unwrap();
             match base_ty {
                 Ty::Array(item_ty, ..) => {
-                    return Node::new(Ast::Addr(Box::new(base)), Some(Ty::Ptr(item_ty)));
+                    return Ok(Node::new(Ast::Addr(Box::new(base)), Some(Ty::Ptr(item_ty))));
                 }
                 _ => {
-                    return Node::new(Ast::Addr(Box::new(base)), Some(Ty::Ptr(Box::new(base_ty))));
+                    return Ok(Node::new(
+                        Ast::Addr(Box::new(base)),
+                        Some(Ty::Ptr(Box::new(base_ty))),
+                    ));
                 }
             }
         }
-        self.primary(var_env)
+        self.postfix(var_env)
     }
 
-    fn primary(&mut self, var_env: &mut VarEnvironment) -> Node {
+    // 配列の添字 x[y] と構造体のメンバ x.y, x->y と後置の x++, x-- を読む
+    fn postfix(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        let node_span = self.token_list.current_span();
+        let mut node = self.primary(var_env)?;
+
+        loop {
+            if let Some(bracket) = self.token_list.try_consume(&TokenKind::LBracket) {
+                // x[y] は *(x + y) として扱う
+                let node_ty = node.ty.clone().unwrap();
+                if !node_ty.is_reference_type() {
+                    return Err(Diagnostic::error(format!(
+                        "Subscripted value of type {:?} is not an array or pointer",
+                        node_ty
+                    ))
+                    .with_span(bracket.span()));
+                }
+                let index_span = self.token_list.current_span();
+                let index = self.expr(var_env)?;
+                Self::check_not_void(&index, index_span)?;
+                self.token_list.expect_kind(&TokenKind::RBracket)?;
+                node = Node::new(
+                    Ast::Deref(Box::new(Self::new_add(node, index))),
+                    Some(node_ty.base_ty()),
+                );
+            } else if let Some(dot) = self.token_list.try_consume(&TokenKind::Dot) {
+                node = self.struct_member(node, dot.span())?;
+            } else if let Some(arrow) = self.token_list.try_consume(&TokenKind::Arrow) {
+                // x->y は (*x).y として扱う
+                let node_ty = node.ty.clone().unwrap();
+                if !node_ty.is_reference_type() {
+                    return Err(Diagnostic::error(format!(
+                        "Member reference type {:?} is not a pointer",
+                        node_ty
+                    ))
+                    .with_span(arrow.span()));
+                }
+                node = Node::new(Ast::Deref(Box::new(node)), Some(node_ty.base_ty()));
+                node = self.struct_member(node, arrow.span())?;
+            } else if let Some(op) = self.token_list.try_consume(&TokenKind::Increment) {
+                Self::check_assignable(&node, node_span)?;
+                node = Self::new_postfix_increment(BinOpType::Add, node, op.span())?;
+            } else if let Some(op) = self.token_list.try_consume(&TokenKind::Decrement) {
+                Self::check_assignable(&node, node_span)?;
+                node = Self::new_postfix_increment(BinOpType::Sub, node, op.span())?;
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    fn struct_member(&mut self, base: Node, op_span: Span) -> Result<Node, Diagnostic> {
+        let struct_ref = match base.ty {
+            Some(Ty::Struct(ref struct_ref) | Ty::Union(ref struct_ref))
+                if struct_ref.is_complete() =>
+            {
+                struct_ref.clone()
+            }
+            _ => {
+                return Err(Diagnostic::error(format!(
+                    "Member reference base type {:?} is not a structure or union",
+                    base.ty.unwrap()
+                ))
+                .with_span(op_span));
+            }
+        };
+        let name_tok = self.token_list.expect_kind(&TokenKind::Ident)?;
+        let name_span = name_tok.span();
+        let name = name_tok.str.unwrap();
+        match struct_ref.member(&name) {
+            Some(Member { name, ty, offset }) => Ok(Node::new(
+                Ast::Member {
+                    base: Box::new(base),
+                    name,
+                    offset,
+                },
+                Some(ty),
+            )),
+            None => Err(
+                Diagnostic::error(format!("No member named {} in {:?}", name, struct_ref))
+                    .with_span(name_span),
+            ),
+        }
+    }
+
+    fn primary(&mut self, var_env: &mut VarEnvironment) -> Result<Node, Diagnostic> {
+        if let Some(va_start_tok) = self.token_list.try_consume(&TokenKind::VaStart) {
+            let (arg_tys, va_area) = match (&self.current_function, self.va_area) {
+                (Some(function_info), Some(va_area)) => (function_info.param_tys.clone(), va_area),
+                _ => {
+                    return Err(Diagnostic::error(
+                        "va_start used in function with fixed arguments",
+                    )
+                    .with_span(va_start_tok.span()));
+                }
+            };
+            self.token_list.expect_kind(&TokenKind::LParen)?;
+            let ap = self.va_list_address(var_env)?;
+            // 最後の固定の引数の名前は、位置を決めるのには使わない
+            self.token_list.expect_kind(&TokenKind::Comma)?;
+            self.assign(var_env)?;
+            self.token_list.expect_kind(&TokenKind::RParen)?;
+            return Ok(Node::new(
+                Ast::VaStart {
+                    ap: Box::new(ap),
+                    arg_tys,
+                    va_area,
+                },
+                Some(Ty::Void),
+            ));
+        }
+        if self.token_list.try_consume(&TokenKind::VaArg).is_some() {
+            self.token_list.expect_kind(&TokenKind::LParen)?;
+            let ap = self.va_list_address(var_env)?;
+            self.token_list.expect_kind(&TokenKind::Comma)?;
+            let ty_span = self.token_list.current_span();
+            let ty = self.type_name(var_env)?;
+            self.token_list.expect_kind(&TokenKind::RParen)?;
+            // 可変長引数は8バイトずつ渡されるので、レジスタに収まる型しか読めない
+            if ty.is_struct_or_union() || matches!(ty, Ty::Array(..) | Ty::Void) {
+                return Err(
+                    Diagnostic::error(format!("Cannot read {:?} with va_arg", ty))
+                        .with_span(ty_span),
+                );
+            }
+            return Ok(Node::new(Ast::VaArg(Box::new(ap)), Some(ty)));
+        }
+        if self.token_list.try_consume(&TokenKind::VaEnd).is_some() {
+            // 後始末は必要ないので、va_listが正しいか確かめるだけ
+            self.token_list.expect_kind(&TokenKind::LParen)?;
+            self.va_list_address(var_env)?;
+            self.token_list.expect_kind(&TokenKind::RParen)?;
+            return Ok(Node::new(Ast::Num(0), Some(Ty::Void)));
+        }
         if self.token_list.try_consume(&TokenKind::LParen).is_some() {
-            let node = self.expr(var_env);
-            self.token_list.expect_kind(&TokenKind::RParen);
-            return node;
+            let node = self.expr(var_env)?;
+            self.token_list.expect_kind(&TokenKind::RParen)?;
+            return Ok(node);
         } else if let Some(ident_tok) = self.token_list.try_consume(&TokenKind::Ident) {
+            let ident_span = ident_tok.span();
             let ident_name = ident_tok.str.unwrap();
             if self.token_list.try_consume(&TokenKind::LParen).is_some() {
-                // 最大6つまでの引数をサポートする
+                // 引数の型を確かめられるように、引数ごとの位置も覚えておく
                 let mut args = vec![];
-                let mut paren_consumed = false;
-                for _ in 1..=6 {
-                    if self.token_list.try_consume(&TokenKind::RParen).is_none() {
-                        let arg = self.expr(var_env);
-                        args.push(arg);
-                        if self.token_list.try_consume(&TokenKind::RParen).is_none() {
-                            self.token_list.expect_kind(&TokenKind::Comma);
-                        } else {
-                            paren_consumed = true;
+                if self.token_list.try_consume(&TokenKind::RParen).is_none() {
+                    loop {
+                        let arg_span = self.token_list.current_span();
+                        let arg = self.assign(var_env)?;
+                        Self::check_not_void(&arg, arg_span)?;
+                        // 構造体はレジスタに収まらないので、値では渡せない
+                        if arg.ty.as_ref().unwrap().is_struct_or_union() {
+                            return Err(Diagnostic::error(format!(
+                                "Passing {:?} by value is not supported",
+                                arg.ty.unwrap()
+                            ))
+                            .with_span(arg_span));
+                        }
+                        args.push((arg, arg_span));
+                        if self.token_list.try_consume(&TokenKind::RParen).is_some() {
                             break;
                         }
-                    } else {
-                        paren_consumed = true;
-                        break;
+                        self.token_list.expect_kind(&TokenKind::Comma)?;
+                    }
+                }
+                // 宣言されている関数なら、引数の数が合っているか確かめる
+                // 宣言されていない関数は、C89と同じように暗黙に宣言されたものとして扱う
+                let function_info = var_env.resolve_function(&ident_name);
+                if let Some(function_info) = function_info.as_ref().filter(|f| f.is_prototyped) {
+                    let expected = function_info.param_tys.len();
+                    if args.len() < expected
+                        || (args.len() > expected && !function_info.is_variadic)
+                    {
+                        return Err(Diagnostic::error(format!(
+                            "Too {} arguments to function call {}, expected {}, have {}",
+                            if args.len() < expected { "few" } else { "many" },
+                            ident_name,
+                            expected,
+                            args.len()
+                        ))
+                        .with_span(ident_span));
                     }
                 }
-                if !paren_consumed {
-                    self.token_list.expect_kind(&TokenKind::RParen);
+                // 引数は宣言された型に変換する。型の分からない引数はcharをintに格上げする
+                let (return_ty, param_tys, fixed_arg_count) = match function_info {
+                    // () で宣言した関数は、宣言されていない関数と同じように引数を渡す
+                    Some(ref function_info) if !function_info.is_prototyped => {
+                        (function_info.return_ty.clone(), vec![], None)
+                    }
+                    Some(ref function_info) => (
+                        function_info.return_ty.clone(),
+                        function_info.param_tys.clone(),
+                        function_info
+                            .is_variadic
+                            .then_some(function_info.param_tys.len()),
+                    ),
+                    // 宣言されていない関数はintを返すものとする
+                    None => (Ty::Int, vec![], None),
+                };
+                let mut converted_args = vec![];
+                for (i, (arg, arg_span)) in args.into_iter().enumerate() {
+                    converted_args.push(match param_tys.get(i) {
+                        Some(param_ty) => {
+                            if !Self::is_assignment_compatible(&arg, param_ty) {
+                                return Err(Diagnostic::error(format!(
+                                    "Passing {:?} to parameter of incompatible type {:?}",
+                                    arg.ty.unwrap(),
+                                    param_ty
+                                ))
+                                .with_span(arg_span)
+                                .with_note(
+                                    self.declared_at(
+                                        &ident_name,
+                                        function_info.as_ref().unwrap().span,
+                                    ),
+                                ));
+                            }
+                            Self::new_cast(arg, param_ty.clone())
+                        }
+                        None if arg.ty == Some(Ty::Char) => Self::new_cast(arg, Ty::Int),
+                        None => arg,
+                    });
                 }
-                // 今はint <function>しかサポートしていない
-                return Node::new(Ast::Funcall(ident_name, args), Some(Ty::Int));
+                return Ok(Node::new(
+                    Ast::Funcall(ident_name, converted_args, fixed_arg_count),
+                    Some(return_ty),
+                ));
             } else if let Some(var_info) = var_env.resolve(&ident_name) {
-                let mut node;
-                let mut node_ty;
-                match var_info {
+                let node = match var_info {
                     VarInfo::Global(GlobalVarInfo {
                         ty: global_var_ty,
                         label,
-                    }) => {
-                        node =
-                            Node::new(Ast::GlobalVar { name: label }, Some(global_var_ty.clone()));
-                        node_ty = global_var_ty;
-                    }
+                        ..
+                    }) => Node::new(Ast::GlobalVar { name: label }, Some(global_var_ty)),
                     VarInfo::Local(LocalVarInfo {
                         ty: local_var_ty,
                         offset,
-                    }) => {
-                        node = Node::new(
-                            Ast::LocalVar {
-                                name: ident_name,
-                                offset,
-                            },
-                            Some(local_var_ty.clone()),
-                        );
-                        node_ty = local_var_ty;
-                    }
-                }
-                // 配列の要素を取りだす構文 x[y][z] をサポート
-                let mut array_dimens = vec![];
-                while self.token_list.try_consume(&TokenKind::LBracket).is_some() {
-                    let dimen = self.token_list.expect_num();
-                    array_dimens.push(dimen);
-                    self.token_list.expect_kind(&TokenKind::RBracket);
-                }
-                for dimen in array_dimens.iter().rev() {
-                    node = Node::new(
-                        Ast::Deref(Box::new(Node::new(
-                            Ast::BinOp(
-                                BinOpType::Add,
-                                Box::new(node),
-                                Box::new(Node::new(Ast::Num(*dimen), Some(Ty::Int))),
-                            ),
-                            Some(node_ty.clone()),
-                        ))),
-                        Some(node_ty.base_ty()),
-                    );
-                    node_ty = node_ty.base_ty();
-                }
-                return node;
+                    }) => Node::new(
+                        Ast::LocalVar {
+                            name: ident_name,
+                            offset,
+                        },
+                        Some(local_var_ty),
+                    ),
+                    VarInfo::EnumConst(value) => Node::new(Ast::Num(value), Some(Ty::Int)),
+                };
+                return Ok(node);
             } else {
-                panic!("undefined variable: {}", ident_name);
+                return Err(
+                    Diagnostic::error(format!("undefined variable: {}", ident_name))
+                        .with_span(ident_span),
+                );
             }
         } else if let Some(str_literal) = self.token_list.try_consume(&TokenKind::String) {
             let label = var_env.add_string_literal(&str_literal.str.clone().unwrap());
-            return Node::new(
-                Ast::StringLiteral { label: label },
+            return Ok(Node::new(
+                Ast::StringLiteral { label },
                 Some(Ty::Array(
                     Box::new(Ty::Char),
                     str_literal.str.unwrap().len() as i32,
                 )),
+            ));
+        }
+
+        let n = self.token_list.expect_num()?;
+        Ok(Node::new(Ast::Num(n), Some(Ty::Int)))
+    }
+
+    // 値を読み書きできるアドレスを持つ式かどうか
+    fn is_lvalue(node: &Node) -> bool {
+        match &node.ast {
+            Ast::LocalVar { .. } | Ast::GlobalVar { .. } | Ast::Deref(..) => true,
+            // 左辺値でない構造体のメンバは左辺値ではない
+            Ast::Member { base, .. } => Self::is_lvalue(base),
+            _ => false,
+        }
+    }
+
+    // 配列そのものには代入できない
+    fn is_assignable(node: &Node) -> bool {
+        Self::is_lvalue(node) && !matches!(node.ty, Some(Ty::Array(..)))
+    }
+
+    fn check_assignable(node: &Node, span: Span) -> Result<(), Diagnostic> {
+        if Self::is_assignable(node) {
+            Ok(())
+        } else {
+            Err(Diagnostic::error("Left side of assignment is not a lvalue").with_span(span))
+        }
+    }
+
+    // nameを宣言した位置を示す注記
+    fn declared_at(&self, name: &str, span: Span) -> String {
+        let location = self.token_list.line_table().locate(span.start);
+        format!(
+            "'{}' is declared at line {}, column {}",
+            name, location.line, location.column
+        )
+    }
+
+    /* Error recovery */
+    // エラーを記録して解析を続ける
+    // これ以上解析を続けられないときは、呼び出し元まで戻るためにErrを返す
+    fn recover(&mut self, diagnostic: Diagnostic) -> Result<(), Diagnostic> {
+        if self.aborted {
+            // 打ち切った後のエラーはすでに記録したエラーの続きなので記録しない
+            return Err(diagnostic);
+        }
+
+        let mut diagnostic = self.token_list.line_table().annotate(diagnostic);
+        if self.error_limit != 0 && self.diagnostics.len() + 1 >= self.error_limit {
+            diagnostic = diagnostic.with_note(format!(
+                "too many errors emitted, stopping now (error limit: {})",
+                self.error_limit
+            ));
+            self.aborted = true;
+        } else if self.token_list.at_end() {
+            self.aborted = true;
+        }
+        self.diagnostics.push(diagnostic.clone());
+
+        if self.aborted {
+            Err(diagnostic)
+        } else {
+            Ok(())
+        }
+    }
+
+    // 文の途中でエラーになったら、; か } まで読み飛ばして次の文から解析を再開する
+    fn synchronize_stmt(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.token_list.peek() {
+            match token.kind {
+                TokenKind::Semicolon if depth == 0 => {
+                    self.token_list.next();
+                    return;
+                }
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => {
+                    // 外側のブロックの } は呼び出し元で読む
+                    if depth == 0 {
+                        return;
+                    }
+                    depth -= 1;
+                    if depth == 0 {
+                        self.token_list.next();
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            self.token_list.next();
+        }
+    }
+
+    // トップレベルでエラーになったら、ブロックの外にある次の型名まで読み飛ばす
+    fn synchronize_top_level(&mut self) {
+        let mut depth: usize = 0;
+        while let Some(token) = self.token_list.peek() {
+            match token.kind {
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => depth = depth.saturating_sub(1),
+                TokenKind::Int
+                | TokenKind::Char
+                | TokenKind::Void
+                | TokenKind::Struct
+                | TokenKind::Union
+                | TokenKind::Enum
+                | TokenKind::Typedef
+                | TokenKind::Extern
+                    if depth == 0 =>
+                {
+                    return
+                }
+                _ => {}
+            }
+            self.token_list.next();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ast, Parser};
+    use crate::{codegen::Target, error_report::Diagnostic, tokenizer::Tokenizer};
+
+    fn diagnostics(source: &str) -> Vec<Diagnostic> {
+        let token_list = Tokenizer::new(source).tokenize().unwrap();
+        Parser::new(token_list)
+            .program()
+            .err()
+            .map_or(vec![], |diagnostics| diagnostics.diagnostics)
+    }
+
+    // 報告されたエラーのメッセージを順に並べる
+    fn error_messages(source: &str) -> Vec<String> {
+        diagnostics(source)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    // 報告されたエラーのメッセージと行番号を順に並べる
+    fn error_lines(source: &str) -> Vec<(String, usize)> {
+        diagnostics(source)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.location.unwrap().line))
+            .collect()
+    }
+
+    // 報告されたエラーのメッセージと注記を順に並べる
+    fn error_notes(source: &str) -> Vec<(String, Vec<String>)> {
+        diagnostics(source)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.notes))
+            .collect()
+    }
+
+    #[test]
+    fn struct_and_union_type_errors() {
+        let messages = error_messages(
+            "struct s { int a; };\nint main() {\n  union s *p;\n  struct s x;\n  return x + 1;\n}\n",
+        );
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with("Use of s with tag type"));
+        assert!(messages[1].starts_with("Invalid operands to binary expression"));
+    }
+
+    #[test]
+    fn struct_tag_redefinition() {
+        let lines = error_lines(
+            "struct s { int a; };\nstruct s { char b; };\nunion u;\nunion u { int a; };\nint main() {\n  struct s { char c; };\n  union u { int a; };\n  union u { int a; };\n  return 0;\n}\n",
+        );
+        assert_eq!(
+            lines,
+            vec![
+                ("Redefinition of struct s".to_owned(), 2),
+                ("Redefinition of union u".to_owned(), 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn sizeof_requires_complete_type() {
+        let lines = error_lines("struct s;\nextern struct s g;\nint main() {\n  sizeof(struct s);\n  return sizeof(g);\n}\n");
+        assert_eq!(
+            lines,
+            vec![
+                (
+                    "Variable has incomplete type Struct(struct s)".to_owned(),
+                    4
+                ),
+                (
+                    "Variable has incomplete type Struct(struct s)".to_owned(),
+                    5
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn function_declaration_errors() {
+        let messages = error_messages("int f(int a, int b);\nint f(int a);\nint g(int a) { return a; }\nint g(int a) { return a; }\nint main() {\n  return g(1, 2);\n}\n");
+        assert_eq!(
+            messages,
+            vec![
+                "Conflicting types for f",
+                "Redefinition of g",
+                "Too many arguments to function call g, expected 1, have 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn unprototyped_function_declarations() {
+        assert_eq!(
+            error_messages("int f();\nint f(int a) { return a; }\nint g();\nint main() {\n  return f(1) + g(1, 2);\n}\n"),
+            Vec::<String>::new()
+        );
+        let messages =
+            error_messages("int f(int a);\nint f();\nchar f();\nint main() {\n  return f();\n}\n");
+        assert_eq!(
+            messages,
+            vec![
+                "Conflicting types for f",
+                "Too few arguments to function call f, expected 1, have 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn argument_type_errors() {
+        let errors = error_notes("struct s { int a; };\nvoid f(char *p);\nint g(struct s *p);\nint main() {\n  struct s v;\n  f(0);\n  f(\"ok\");\n  f(5);\n  return g(v.a);\n}\n");
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "Passing Int to parameter of incompatible type Ptr(Char)".to_owned(),
+                    vec!["'f' is declared at line 2, column 6".to_owned()]
+                ),
+                (
+                    "Passing Int to parameter of incompatible type Ptr(Struct(struct s))"
+                        .to_owned(),
+                    vec!["'g' is declared at line 3, column 5".to_owned()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn pointer_arguments_must_point_to_compatible_types() {
+        let messages = error_messages("struct s { int a; };\nstruct t { int a; };\nint f(int *p);\nint g(struct s *p);\nint h(void *p);\nint main() {\n  int *p;\n  char *q;\n  void *v;\n  struct t *t;\n  f(p);\n  f(v);\n  h(q);\n  f(q);\n  g(t);\n  return 0;\n}\n");
+        assert_eq!(
+            messages,
+            vec![
+                "Passing Ptr(Char) to parameter of incompatible type Ptr(Int)",
+                "Passing Ptr(Struct(struct t)) to parameter of incompatible type Ptr(Struct(struct s))",
+            ]
+        );
+    }
+
+    #[test]
+    fn global_variable_conflicts() {
+        let errors = error_notes(
+            "int g;\nchar g;\nextern int h;\nchar *h;\nint h;\nint main() { return 0; }\n",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "conflicting types for 'g'".to_owned(),
+                    vec!["'g' is declared at line 1, column 5".to_owned()]
+                ),
+                (
+                    "conflicting types for 'h'".to_owned(),
+                    vec!["'h' is declared at line 3, column 12".to_owned()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn redeclaration_in_same_scope() {
+        let errors = error_lines(
+            "enum { A, A };\nint g;\nenum { g };\nint f(int x, int x) { return 0; }\nint main() {\n  int y;\n  char y;\n  { int y; }\n  return 0;\n}\n",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                ("Redefinition of 'A'".to_owned(), 1),
+                ("Redefinition of 'g'".to_owned(), 3),
+                ("Redefinition of 'x'".to_owned(), 4),
+                ("Redefinition of 'y'".to_owned(), 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn return_type_errors() {
+        let messages =
+            error_messages("void f() {\n  return 1;\n}\nint g() {\n  void x;\n  return;\n}\n");
+        assert_eq!(
+            messages,
+            vec![
+                "Void function should not return a value",
+                "Variable has incomplete type Void",
+                "Non-void function should return a value of type Int",
+            ]
+        );
+    }
+
+    #[test]
+    fn return_value_must_convert_to_return_type() {
+        let lines = error_lines("struct s { int a; };\nint f() {\n  struct s v;\n  return v;\n}\nchar *g() {\n  return 5;\n}\nchar *h() {\n  return 0;\n}\n");
+        assert_eq!(
+            lines,
+            vec![
+                (
+                    "Returning Struct(struct s) from a function with incompatible result type Int"
+                        .to_owned(),
+                    4
+                ),
+                (
+                    "Returning Int from a function with incompatible result type Ptr(Char)"
+                        .to_owned(),
+                    7
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn void_value_is_not_a_value() {
+        let lines = error_lines("void f();\nint g(int a);\nint main() {\n  int x;\n  f();\n  x = f();\n  x = f() + 1;\n  g(f());\n  if (f()) x = 1;\n  x = f() == 0;\n  x = -f();\n  return f();\n}\n");
+        assert_eq!(
+            lines,
+            vec![
+                ("Void value not ignored as it ought to be".to_owned(), 6),
+                (
+                    "Invalid operands to binary expression (Void and Int)".to_owned(),
+                    7
+                ),
+                ("Void value not ignored as it ought to be".to_owned(), 8),
+                ("Void value not ignored as it ought to be".to_owned(), 9),
+                (
+                    "Invalid operands to binary expression (Void and Int)".to_owned(),
+                    10
+                ),
+                (
+                    "Invalid operands to binary expression (Int and Void)".to_owned(),
+                    11
+                ),
+                (
+                    "Returning Void from a function with incompatible result type Int".to_owned(),
+                    12
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn cast_operand_must_be_scalar() {
+        let lines = error_lines("struct s { int a; };\nvoid f();\nint main() {\n  struct s v;\n  (void)v;\n  (void)f();\n  (char)v;\n  (int)\n    v;\n  return (int)f();\n}\n");
+        assert_eq!(
+            lines,
+            vec![
+                (
+                    "Operand of type Struct(struct s) where arithmetic or pointer type is required"
+                        .to_owned(),
+                    7
+                ),
+                (
+                    "Operand of type Struct(struct s) where arithmetic or pointer type is required"
+                        .to_owned(),
+                    9
+                ),
+                (
+                    "Operand of type Void where arithmetic or pointer type is required".to_owned(),
+                    10
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn va_start_requires_variadic_function() {
+        let messages = error_messages("int f(int n) {\n  va_list ap;\n  va_start(ap, n);\n  return 0;\n}\nint g(int n, ...) {\n  int x;\n  return va_arg(x, int);\n}\n");
+        assert_eq!(
+            messages,
+            vec![
+                "va_start used in function with fixed arguments",
+                "Expected va_list, but got Int",
+            ]
+        );
+    }
+
+    #[test]
+    fn va_list_depends_on_target() {
+        let source = "int main() { va_list ap; return sizeof(ap); }";
+        for (target, size) in [
+            (Target::Aarch64AppleDarwin, 8),
+            (Target::Aarch64LinuxGnu, 32),
+            (Target::X86_64LinuxGnu, 24),
+        ] {
+            let token_list = Tokenizer::new(source).tokenize().unwrap();
+            let (program, _) = Parser::new(token_list)
+                .with_target(target)
+                .program()
+                .unwrap();
+            let Ast::Fundef { ref body, .. } = program[0].ast else {
+                panic!("expected function definition");
+            };
+            let Ast::Return(Some(ref value)) = body[1].ast else {
+                panic!("expected return statement");
+            };
+            assert_eq!(value.ast, Ast::Num(size));
+        }
+    }
+
+    #[test]
+    fn bitwise_operands_must_be_integers() {
+        let messages =
+            error_messages("int main() {\n  int *p;\n  p = 0;\n  return (p & 1) + ~p;\n}\n");
+        assert_eq!(
+            messages,
+            vec!["Invalid operands to binary expression (Ptr(Int) and Int)"]
+        );
+    }
+
+    #[test]
+    fn compound_assignment_errors() {
+        let messages = error_messages(
+            "int main() {\n  int *p;\n  int a;\n  p *= 2;\n  a += p;\n  1++;\n  return 0;\n}\n",
+        );
+        assert_eq!(
+            messages,
+            vec![
+                "Invalid operands to binary expression (Ptr(Int) and Int)",
+                "Invalid operands to binary expression (Int and Ptr(Int))",
+                "Left side of assignment is not a lvalue",
+            ]
+        );
+    }
+
+    #[test]
+    fn statement_condition_must_be_scalar() {
+        for stmt in [
+            "if (v) return 1;",
+            "while (v) return 2;",
+            "for (; v;) return 3;",
+            "do return 4; while (v);",
+        ] {
+            let source = format!(
+                "struct s {{ int a; }};\nint main() {{\n  struct s v;\n  {}\n  return 0;\n}}\n",
+                stmt
+            );
+            // forは条件の後の ) から読み直すので、最初のエラーだけを見る
+            assert_eq!(
+                error_lines(&source)[0],
+                (
+                    "Used type Struct(struct s) where arithmetic or pointer type is required"
+                        .to_owned(),
+                    4
+                )
             );
         }
+    }
+
+    #[test]
+    fn conditional_operand_types_must_be_compatible() {
+        let messages =
+            error_messages("int main() {\n  int *p;\n  char *s;\n  return *(1 ? p : s);\n}\n");
+        assert_eq!(
+            messages,
+            vec!["Incompatible operand types (Ptr(Int) and Ptr(Char))"]
+        );
+    }
+
+    #[test]
+    fn conditional_integer_must_be_null_pointer_constant() {
+        let messages = error_messages(
+            "int main() {\n  int *p;\n  int x;\n  p = 1 ? p : 0;\n  p = 1 ? 0 : p;\n  p = 1 ? p : x;\n  return 0;\n}\n",
+        );
+        assert_eq!(
+            messages,
+            vec!["Incompatible operand types (Ptr(Int) and Int)"]
+        );
+    }
 
-        let n = self.token_list.expect_num();
-        Node::new(Ast::Num(n), Some(Ty::Int))
+    #[test]
+    fn break_and_continue_outside_loop() {
+        let lines = error_lines(
+            "int main() {\n  break;\n  while (1) break;\n  continue;\n  return 0;\n}\n",
+        );
+        assert_eq!(
+            lines,
+            vec![
+                (
+                    "'break' statement not in loop or switch statement".to_owned(),
+                    2
+                ),
+                ("'continue' statement not in loop statement".to_owned(), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn member_of_rvalue_is_not_lvalue() {
+        assert_eq!(
+            error_messages("struct s { int a; }; int main() { struct s x; struct s y; (x = y).a = 1; return 0; }"),
+            vec!["Left side of assignment is not a lvalue"]
+        );
+    }
+
+    #[test]
+    fn enum_value_overflow() {
+        assert_eq!(
+            error_messages("enum { A = 2147483647 }; enum { B = 2147483647, C = 0, D }; int main() { return A; }"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            error_messages("enum { A = 2147483647, B }; int main() { return 0; }"),
+            vec!["Enumerator value for 'B' is not representable as int"]
+        );
+    }
+
+    #[test]
+    fn switch_errors() {
+        let lines = error_lines("int main() {\n  int *p;\n  case 1: break;\n  switch (1) {\n  case 1: break;\n  case 2 - 1: break;\n  default: break;\n  default: break;\n  }\n  switch (p) { }\n  return 0;\n}\n");
+        assert_eq!(
+            lines,
+            vec![
+                ("'case' statement not in switch statement".to_owned(), 3),
+                ("Duplicate case value 1".to_owned(), 6),
+                ("Multiple default labels in one switch".to_owned(), 8),
+                (
+                    "Statement requires expression of integer type (Ptr(Int) invalid)".to_owned(),
+                    10
+                ),
+            ]
+        );
     }
 }
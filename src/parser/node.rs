@@ -7,16 +7,33 @@ pub enum BinOpType {
     Sub,
     Mul,
     Div,
+    // 剰余の符号は被除数に合わせる(C99)
+    Mod,
     Equal,
     NotEqual,
     LessThan,
     LessThanOrEqual,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    // 算術シフト
+    ShiftRight,
 }
 
 #[derive(PartialEq, Eq, Debug)]
 pub enum Ast {
     BinOp(BinOpType, Box<Node>, Box<Node>),
     Assign(Box<Node>, Box<Node>),
+    // a op= b。左辺のアドレスは一度だけ評価する
+    CompoundAssign(BinOpType, Box<Node>, Box<Node>),
+    // 左辺で結果が決まる場合は右辺を評価しない
+    LogicalAnd(Box<Node>, Box<Node>),
+    LogicalOr(Box<Node>, Box<Node>),
+    // cond ? then : else
+    Conditional(Box<Node>, Box<Node>, Box<Node>),
+    // 左辺を評価して捨ててから右辺を評価する
+    Comma(Box<Node>, Box<Node>),
     LocalVar {
         name: String,
         offset: i32,
@@ -28,9 +45,27 @@ pub enum Ast {
         label: String,
     },
     Num(i32),
-    Return(Box<Node>),
+    // void関数のreturnは値を持たない
+    Return(Option<Box<Node>>),
     If(Box<Node>, Box<Node>, Option<Box<Node>>),
+    // 一番内側のループかswitchを抜ける、または次の繰り返しに進む
+    Break,
+    Continue,
+    // case_valuesの添字がそれぞれのcaseのindexになる
+    Switch {
+        condition: Box<Node>,
+        body: Box<Node>,
+        case_values: Vec<i32>,
+        has_default: bool,
+    },
+    Case {
+        index: usize,
+        body: Box<Node>,
+    },
+    Default(Box<Node>),
     While(Box<Node>, Box<Node>),
+    // 本体を実行してから条件を評価する
+    DoWhile(Box<Node>, Box<Node>),
     For(
         Option<Box<Node>>,
         Option<Box<Node>>,
@@ -38,15 +73,34 @@ pub enum Ast {
         Box<Node>,
     ),
     Block(Vec<Node>),
-    Funcall(String, Vec<Node>),
+    // 可変長引数の関数の呼び出しなら、固定の引数の数を持つ
+    Funcall(String, Vec<Node>, Option<usize>),
     Fundef {
         name: String,
         args: Vec<Node>,
         body: Vec<Node>,
         stack_size: i32,
+        // 可変長引数の関数なら、引数のレジスタを保存する領域のoffset
+        va_area: Option<i32>,
+    },
+    // apはva_listのアドレス
+    VaStart {
+        ap: Box<Node>,
+        arg_tys: Vec<Ty>,
+        va_area: i32,
     },
+    // 次の可変長引数をNodeの型で読む
+    VaArg(Box<Node>),
     Addr(Box<Node>),
     Deref(Box<Node>),
+    // 値をNodeの型に変換する
+    Cast(Box<Node>),
+    BitNot(Box<Node>),
+    Member {
+        base: Box<Node>,
+        name: String,
+        offset: i32,
+    },
     LocalVarDef(String, Ty),
     GlobalVarDef(String, Ty),
 }
@@ -1,24 +1,61 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Ty {
     Int,
     Char,
+    Void,
     Ptr(Box<Ty>),
     Array(Box<Ty>, i32),
+    Struct(StructRef),
+    Union(StructRef),
 }
 
 impl Ty {
+    // 型を作るときにchecked_sizeでサイズがi32に収まることを確かめておく
     pub fn size(&self) -> i32 {
+        self.checked_size()
+            .unwrap_or_else(|| panic!("size of {:?} does not fit in i32", self))
+    }
+
+    // サイズがi32に収まらなければNoneを返す
+    pub fn checked_size(&self) -> Option<i32> {
+        match self {
+            // void *の演算はGCCと同じように1バイト単位で行う
+            Ty::Char | Ty::Void => Some(1),
+            Ty::Int => Some(4),
+            Ty::Ptr(_) => Some(8),
+            Ty::Array(ty, len) => ty.checked_size()?.checked_mul(*len),
+            Ty::Struct(struct_ref) | Ty::Union(struct_ref) => Some(struct_ref.0.borrow().size),
+        }
+    }
+
+    // 型の値を置けるアドレスの境界
+    pub fn align(&self) -> i32 {
         match self {
-            Ty::Char => 1,
+            Ty::Char | Ty::Void => 1,
             Ty::Int => 4,
             Ty::Ptr(_) => 8,
-            Ty::Array(ty, len) => {
-                let ty_size = ty.size();
-                ty_size * len
-            }
+            Ty::Array(ty, _) => ty.align(),
+            Ty::Struct(struct_ref) | Ty::Union(struct_ref) => struct_ref.0.borrow().align,
+        }
+    }
+
+    // メンバの定義が終わっていない構造体は、ポインタを通してしか使えない
+    pub fn is_complete(&self) -> bool {
+        match self {
+            Ty::Array(ty, _) => ty.is_complete(),
+            Ty::Struct(struct_ref) | Ty::Union(struct_ref) => struct_ref.is_complete(),
+            Ty::Void => false,
+            _ => true,
         }
     }
 
+    // メンバを持つ型。値はレジスタに収まらないのでアドレスのまま扱う
+    pub fn is_struct_or_union(&self) -> bool {
+        matches!(self, Ty::Struct(_) | Ty::Union(_))
+    }
+
     pub fn is_reference_type(&self) -> bool {
         matches!(self, Ty::Ptr(_) | Ty::Array(..))
     }
@@ -31,3 +68,218 @@ impl Ty {
         }
     }
 }
+
+// nをalignの倍数に切り上げる
+pub fn align_to(n: i32, align: i32) -> i32 {
+    (n + align - 1) / align * align
+}
+
+// 切り上げた結果がi32に収まらなければNoneを返す
+pub fn checked_align_to(n: i32, align: i32) -> Option<i32> {
+    Some(n.checked_add(align - 1)? / align * align)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Member {
+    pub name: String,
+    pub ty: Ty,
+    pub offset: i32,
+}
+
+struct StructTy {
+    tag: Option<String>,
+    // 共用体は全てのメンバをoffset 0に置く
+    is_union: bool,
+    members: Vec<Member>,
+    size: i32,
+    align: i32,
+    is_complete: bool,
+}
+
+// 構造体と共用体の中身
+// 構造体は自分自身へのポインタをメンバに持てるように、
+// タグを宣言した時点の型を共有しておき、メンバの定義が終わったら中身を埋める
+#[derive(Clone)]
+pub struct StructRef(Rc<RefCell<StructTy>>);
+
+impl StructRef {
+    pub fn new(tag: Option<String>) -> Self {
+        Self::new_with_kind(tag, false)
+    }
+
+    pub fn new_union(tag: Option<String>) -> Self {
+        Self::new_with_kind(tag, true)
+    }
+
+    fn new_with_kind(tag: Option<String>, is_union: bool) -> Self {
+        Self(Rc::new(RefCell::new(StructTy {
+            tag,
+            is_union,
+            members: vec![],
+            size: 0,
+            align: 1,
+            is_complete: false,
+        })))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.0.borrow().is_complete
+    }
+
+    pub fn is_union(&self) -> bool {
+        self.0.borrow().is_union
+    }
+
+    pub fn ty(&self) -> Ty {
+        if self.is_union() {
+            Ty::Union(self.clone())
+        } else {
+            Ty::Struct(self.clone())
+        }
+    }
+
+    // 構造体はメンバを宣言順に、それぞれの型のアラインメントに合わせて配置する
+    // 共用体はメンバを全て先頭に重ねて配置する
+    // 全体のサイズは、メンバの最大のアラインメントの倍数に切り上げる
+    // サイズがi32に収まらなければ定義せずにfalseを返す
+    #[must_use]
+    pub fn define(&self, members: Vec<(String, Ty)>) -> bool {
+        let mut struct_ty = self.0.borrow_mut();
+        let is_union = struct_ty.is_union;
+        let mut size = 0;
+        let mut align = 1;
+        let mut laid_out = vec![];
+        for (name, ty) in members {
+            let offset = if is_union {
+                0
+            } else {
+                match checked_align_to(size, ty.align()) {
+                    Some(offset) => offset,
+                    None => return false,
+                }
+            };
+            let end = match offset.checked_add(ty.size()) {
+                Some(end) => end,
+                None => return false,
+            };
+            align = align.max(ty.align());
+            size = size.max(end);
+            laid_out.push(Member { name, ty, offset });
+        }
+        let size = match checked_align_to(size, align) {
+            Some(size) => size,
+            None => return false,
+        };
+        struct_ty.members = laid_out;
+        struct_ty.size = size;
+        struct_ty.align = align;
+        struct_ty.is_complete = true;
+
+        true
+    }
+
+    pub fn member(&self, name: &str) -> Option<Member> {
+        self.0
+            .borrow()
+            .members
+            .iter()
+            .find(|member| member.name == name)
+            .cloned()
+    }
+}
+
+// 構造体や共用体の型は同じ宣言から作られたものだけが等しい
+impl PartialEq for StructRef {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for StructRef {}
+
+// メンバに自分自身へのポインタを持つことがあるので、タグだけを表示する
+impl fmt::Debug for StructRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let struct_ty = self.0.borrow();
+        let keyword = if struct_ty.is_union {
+            "union"
+        } else {
+            "struct"
+        };
+        match struct_ty.tag {
+            Some(ref tag) => write!(f, "{} {}", keyword, tag),
+            None => write!(f, "{} <anonymous>", keyword),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StructRef, Ty};
+
+    #[test]
+    fn struct_layout_with_natural_alignment() {
+        let struct_ref = StructRef::new(Some("s".to_owned()));
+        assert!(struct_ref.define(vec![
+            ("c".to_owned(), Ty::Char),
+            ("p".to_owned(), Ty::Ptr(Box::new(Ty::Int))),
+            ("i".to_owned(), Ty::Int),
+        ]));
+        let ty = Ty::Struct(struct_ref.clone());
+        assert_eq!(struct_ref.member("c").unwrap().offset, 0);
+        assert_eq!(struct_ref.member("p").unwrap().offset, 8);
+        assert_eq!(struct_ref.member("i").unwrap().offset, 16);
+        assert_eq!(ty.size(), 24);
+        assert_eq!(ty.align(), 8);
+    }
+
+    #[test]
+    fn self_referential_struct() {
+        let struct_ref = StructRef::new(Some("node".to_owned()));
+        let next_ty = Ty::Ptr(Box::new(Ty::Struct(struct_ref.clone())));
+        assert!(!Ty::Struct(struct_ref.clone()).is_complete());
+        assert!(struct_ref.define(vec![
+            ("val".to_owned(), Ty::Char),
+            ("next".to_owned(), next_ty.clone()),
+        ]));
+        assert!(next_ty.base_ty().is_complete());
+        assert_eq!(next_ty.base_ty().size(), 16);
+        assert_eq!(format!("{:?}", next_ty), "Ptr(Struct(struct node))");
+    }
+
+    #[test]
+    fn union_layout() {
+        let union_ref = StructRef::new_union(Some("u".to_owned()));
+        assert!(union_ref.define(vec![
+            ("c".to_owned(), Ty::Char),
+            ("a".to_owned(), Ty::Array(Box::new(Ty::Char), 5)),
+            ("i".to_owned(), Ty::Int),
+        ]));
+        let ty = union_ref.ty();
+        assert_eq!(union_ref.member("a").unwrap().offset, 0);
+        assert_eq!(union_ref.member("i").unwrap().offset, 0);
+        assert_eq!(ty.size(), 8);
+        assert_eq!(ty.align(), 4);
+        assert_eq!(format!("{:?}", ty), "Union(union u)");
+    }
+
+    #[test]
+    fn too_large_types() {
+        let ty = Ty::Array(
+            Box::new(Ty::Array(
+                Box::new(Ty::Array(Box::new(Ty::Int), 1000)),
+                1000,
+            )),
+            1000,
+        );
+        assert_eq!(ty.checked_size(), None);
+
+        let struct_ref = StructRef::new(Some("s".to_owned()));
+        let member_ty = Ty::Array(Box::new(Ty::Int), 300_000_000);
+        assert!(!struct_ref.define(vec![
+            ("x".to_owned(), member_ty.clone()),
+            ("y".to_owned(), member_ty),
+        ]));
+        assert!(!struct_ref.is_complete());
+    }
+}
@@ -1,4 +1,5 @@
-use super::ty::Ty;
+use super::ty::{align_to, checked_align_to, StructRef, Ty};
+use crate::error_report::Span;
 use std::collections::HashMap;
 
 const STACK_ALIGNMENT: i32 = 16;
@@ -13,6 +14,8 @@ pub struct LocalVarInfo {
 pub struct GlobalVarInfo {
     pub ty: Ty,
     pub label: String,
+    // 最初に宣言した位置
+    pub span: Span,
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -21,17 +24,50 @@ pub struct StringLiteralEntry {
     pub label: String,
 }
 
+// 関数の宣言。プロトタイプか定義を読んだ時点で登録する
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct FunctionInfo {
+    pub name: String,
+    pub return_ty: Ty,
+    pub param_tys: Vec<Ty>,
+    // 引数の最後が ... の関数
+    pub is_variadic: bool,
+    // int f(); のように () で宣言した関数は、引数の数も型も分からない
+    pub is_prototyped: bool,
+    pub is_defined: bool,
+    pub span: Span,
+}
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum VarInfo {
     Local(LocalVarInfo),
     Global(GlobalVarInfo),
+    // enumの定数
+    EnumConst(i32),
+}
+
+// 変数、enumの定数、typedefで宣言した型の名前は同じ名前空間に入る
+#[derive(Clone)]
+enum Declaration {
+    Var(VarInfo),
+    Typedef(Ty),
+}
+
+// { } で囲まれたブロックのスコープ。一番外側はファイルのスコープになる
+#[derive(Clone, Default)]
+struct Scope {
+    names: HashMap<String, Declaration>,
+    // 構造体と共用体のタグ
+    struct_tags: HashMap<String, StructRef>,
 }
 
 #[derive(Clone)]
 pub struct VarEnvironment {
     stack_offset: i32,
-    local_variables: HashMap<String, LocalVarInfo>,
-    global_variables: HashMap<String, GlobalVarInfo>,
+    // 内側のスコープほど後ろにある。名前は内側のスコープから順に探す
+    scopes: Vec<Scope>,
+    // 関数はグローバルなスコープにだけ宣言できる
+    functions: HashMap<String, FunctionInfo>,
     pub string_literals: Vec<StringLiteralEntry>,
 }
 
@@ -39,48 +75,88 @@ impl VarEnvironment {
     pub fn new() -> Self {
         Self {
             stack_offset: 16,
-            local_variables: HashMap::new(),
-            global_variables: HashMap::new(),
+            scopes: vec![Scope::default()],
+            functions: HashMap::new(),
             string_literals: vec![],
         }
     }
 
+    fn current_scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn file_scope(&mut self) -> &mut Scope {
+        self.scopes.first_mut().unwrap()
+    }
+
+    // 内側のスコープから順に名前を探す
+    fn find(&self, name: &str) -> Option<&Declaration> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.names.get(name))
+    }
+
     // スタックのアラインメントに現在のoffsetをアラインした数を返す
     // たとえば5なら16, 16なら16, 17なら32
     pub fn stack_size(&self) -> i32 {
-        (self.stack_offset + STACK_ALIGNMENT - 1) / STACK_ALIGNMENT * STACK_ALIGNMENT
+        align_to(self.stack_offset, STACK_ALIGNMENT)
+    }
+
+    // tyの変数を足してもスタックの大きさがi32に収まるか
+    pub fn can_add_local_var(&self, ty: &Ty) -> bool {
+        self.stack_offset
+            .checked_add(ty.size())
+            .and_then(|offset| checked_align_to(offset, ty.align()))
+            .and_then(|offset| checked_align_to(offset, STACK_ALIGNMENT))
+            .is_some()
     }
 
+    // ブロックを抜けた後も領域は再利用せず、関数の中の変数にはすべて別のoffsetを割り当てる
     pub fn add_local_var(&mut self, name: &str, ty: Ty) -> LocalVarInfo {
-        if let Some(var_info) = self.local_variables.get(name) {
+        if let Some(Declaration::Var(VarInfo::Local(var_info))) =
+            self.current_scope().names.get(name)
+        {
             var_info.clone()
         } else {
-            let size = ty.size();
-            self.stack_offset += size;
+            // FPは16バイト境界にあるので、offsetを型のアラインメントの倍数にすればアドレスも揃う
+            self.stack_offset = align_to(self.stack_offset + ty.size(), ty.align());
             let var_info = LocalVarInfo {
                 ty,
                 offset: self.stack_offset,
             };
-            self.local_variables
-                .insert(name.to_string(), var_info.clone());
+            self.current_scope().names.insert(
+                name.to_string(),
+                Declaration::Var(VarInfo::Local(var_info.clone())),
+            );
             var_info
         }
     }
 
-    pub fn add_global_var(&mut self, name: &str, ty: Ty) -> GlobalVarInfo {
-        if let Some(var_info) = self.global_variables.get(name) {
-            var_info.clone()
+    pub fn add_global_var(&mut self, name: &str, ty: Ty, span: Span) -> GlobalVarInfo {
+        if let Some(var_info) = self.resolve_global_var(name) {
+            var_info
         } else {
             let var_info = GlobalVarInfo {
                 ty,
                 label: name.to_owned(),
+                span,
             };
-            self.global_variables
-                .insert(name.to_string(), var_info.clone());
+            self.file_scope().names.insert(
+                name.to_string(),
+                Declaration::Var(VarInfo::Global(var_info.clone())),
+            );
             var_info
         }
     }
 
+    pub fn resolve_global_var(&self, name: &str) -> Option<GlobalVarInfo> {
+        match self.scopes[0].names.get(name) {
+            Some(Declaration::Var(VarInfo::Global(var_info))) => Some(var_info.clone()),
+            _ => None,
+        }
+    }
+
     pub fn add_string_literal(&mut self, str: &str) -> String {
         let label = format!("l_.str.{}", self.string_literals.len());
         self.string_literals.push(StringLiteralEntry {
@@ -91,27 +167,95 @@ impl VarEnvironment {
         label
     }
 
+    pub fn add_enum_constant(&mut self, name: &str, value: i32) {
+        self.current_scope()
+            .names
+            .insert(name.to_owned(), Declaration::Var(VarInfo::EnumConst(value)));
+    }
+
+    // 変数、定数、型の名前が今のスコープで宣言されているか
+    pub fn is_declared_in_current_scope(&self, name: &str) -> bool {
+        self.scopes.last().unwrap().names.contains_key(name)
+    }
+
     // 変数の名前を解決する
-    // ローカル変数は同名のグローバル変数をシャドーイングするので、まずはローカルの変数を探す
+    // 内側のスコープで宣言した名前は、外側のスコープの同じ名前を隠す
     pub fn resolve(&self, name: &str) -> Option<VarInfo> {
-        if let Some(local_var_info) = self.local_variables.get(name) {
-            Some(VarInfo::Local(local_var_info.clone()))
-        } else {
-            self.global_variables
-                .get(name)
-                .map(|global_var_info| VarInfo::Global(global_var_info.clone()))
+        match self.find(name) {
+            Some(Declaration::Var(var_info)) => Some(var_info.clone()),
+            _ => None,
         }
     }
 
-    pub fn clear_local_variables(&mut self) {
-        self.local_variables.clear()
+    pub fn add_typedef(&mut self, name: &str, ty: Ty) {
+        self.current_scope()
+            .names
+            .insert(name.to_owned(), Declaration::Typedef(ty));
+    }
+
+    // 名前が型を表すならその型を返す
+    // 内側のスコープで同じ名前の変数や定数を宣言していれば、外側の型の名前は隠れる
+    pub fn resolve_typedef(&self, name: &str) -> Option<Ty> {
+        match self.find(name) {
+            Some(Declaration::Typedef(ty)) => Some(ty.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn add_function(&mut self, function_info: FunctionInfo) {
+        self.functions
+            .insert(function_info.name.clone(), function_info);
+    }
+
+    pub fn resolve_function(&self, name: &str) -> Option<FunctionInfo> {
+        self.functions.get(name).cloned()
+    }
+
+    pub fn add_struct_tag(&mut self, tag: &str, struct_ref: StructRef) {
+        self.current_scope()
+            .struct_tags
+            .insert(tag.to_owned(), struct_ref);
+    }
+
+    pub fn resolve_struct_tag(&self, tag: &str) -> Option<StructRef> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.struct_tags.get(tag))
+            .cloned()
+    }
+
+    pub fn resolve_struct_tag_in_current_scope(&self, tag: &str) -> Option<StructRef> {
+        self.scopes.last().unwrap().struct_tags.get(tag).cloned()
+    }
+
+    // 関数の引数は関数のスコープに追加する
+    pub fn enter_function(&mut self) {
+        self.scopes.truncate(1);
+        self.enter_block();
+    }
+
+    // エラーで途中のブロックを抜けていても、ファイルのスコープに戻る
+    pub fn leave_function(&mut self) {
+        self.scopes.truncate(1);
+    }
+
+    pub fn enter_block(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    pub fn leave_block(&mut self) {
+        self.scopes.pop();
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{GlobalVarInfo, LocalVarInfo, VarEnvironment, VarInfo};
-    use crate::parser::Ty;
+    use super::{FunctionInfo, GlobalVarInfo, LocalVarInfo, VarEnvironment, VarInfo};
+    use crate::{
+        error_report::Span,
+        parser::{ty::StructRef, Ty},
+    };
 
     #[test]
     fn add_local_var() {
@@ -129,10 +273,11 @@ mod test {
     fn add_global_var() {
         let mut var_env = VarEnvironment::new();
         assert_eq!(
-            var_env.add_global_var("x", Ty::Int),
+            var_env.add_global_var("x", Ty::Int, Span::new(4, 1)),
             GlobalVarInfo {
                 ty: Ty::Int,
                 label: "x".to_owned(),
+                span: Span::new(4, 1),
             }
         );
     }
@@ -153,12 +298,13 @@ mod test {
     #[test]
     fn resolve_global_var() {
         let mut var_env = VarEnvironment::new();
-        var_env.add_global_var("x", Ty::Int);
+        var_env.add_global_var("x", Ty::Int, Span::new(4, 1));
         assert_eq!(
             var_env.resolve("x"),
             Some(VarInfo::Global(GlobalVarInfo {
                 ty: Ty::Int,
                 label: "x".to_owned(),
+                span: Span::new(4, 1),
             }))
         );
     }
@@ -166,8 +312,126 @@ mod test {
     #[test]
     fn lobal_var_shadowing_global_var() {
         let mut var_env = VarEnvironment::new();
-        var_env.add_global_var("x", Ty::Int);
+        var_env.add_global_var("x", Ty::Int, Span::new(4, 1));
+        var_env.enter_function();
+        var_env.add_local_var("x", Ty::Int);
+        assert_eq!(
+            var_env.resolve("x"),
+            Some(VarInfo::Local(LocalVarInfo {
+                ty: Ty::Int,
+                offset: 20,
+            }))
+        );
+    }
+
+    #[test]
+    fn align_local_var() {
+        let mut var_env = VarEnvironment::new();
+        var_env.add_local_var("c", Ty::Char);
+        assert_eq!(
+            var_env
+                .add_local_var("p", Ty::Ptr(Box::new(Ty::Int)))
+                .offset,
+            32
+        );
+    }
+
+    #[test]
+    fn stack_frame_must_fit_in_i32() {
+        let mut var_env = VarEnvironment::new();
+        let big_ty = Ty::Array(Box::new(Ty::Int), 300_000_000);
+        assert!(var_env.can_add_local_var(&big_ty));
+        var_env.add_local_var("a", big_ty.clone());
+        assert!(!var_env.can_add_local_var(&big_ty));
+    }
+
+    #[test]
+    fn local_struct_tag_shadowing_global_struct_tag() {
+        let mut var_env = VarEnvironment::new();
+        let global = StructRef::new(Some("s".to_owned()));
+        var_env.add_struct_tag("s", global.clone());
+        var_env.enter_function();
+        let local = StructRef::new(Some("s".to_owned()));
+        var_env.add_struct_tag("s", local.clone());
+        assert_eq!(var_env.resolve_struct_tag("s"), Some(local));
+        var_env.leave_function();
+        var_env.enter_function();
+        assert_eq!(var_env.resolve_struct_tag("s"), Some(global));
+    }
+
+    #[test]
+    fn resolve_enum_constant() {
+        let mut var_env = VarEnvironment::new();
+        var_env.add_enum_constant("RED", 0);
+        var_env.enter_function();
+        var_env.add_enum_constant("GREEN", 5);
+        assert_eq!(var_env.resolve("RED"), Some(VarInfo::EnumConst(0)));
+        assert_eq!(var_env.resolve("GREEN"), Some(VarInfo::EnumConst(5)));
+        var_env.add_local_var("RED", Ty::Int);
+        assert!(matches!(var_env.resolve("RED"), Some(VarInfo::Local(_))));
+        var_env.leave_function();
+        var_env.enter_function();
+        assert_eq!(var_env.resolve("GREEN"), None);
+    }
+
+    #[test]
+    fn local_var_hides_global_typedef() {
+        let mut var_env = VarEnvironment::new();
+        var_env.add_typedef("T", Ty::Ptr(Box::new(Ty::Int)));
+        var_env.enter_function();
+        assert_eq!(
+            var_env.resolve_typedef("T"),
+            Some(Ty::Ptr(Box::new(Ty::Int)))
+        );
+        var_env.add_local_var("T", Ty::Int);
+        assert_eq!(var_env.resolve_typedef("T"), None);
+        var_env.add_typedef("U", Ty::Char);
+        assert_eq!(var_env.resolve_typedef("U"), Some(Ty::Char));
+        var_env.leave_function();
+        var_env.enter_function();
+        assert_eq!(var_env.resolve_typedef("U"), None);
+    }
+
+    #[test]
+    fn block_scope_ends_at_leave_block() {
+        let mut var_env = VarEnvironment::new();
+        var_env.add_typedef("T", Ty::Int);
+        var_env.enter_function();
+        var_env.enter_block();
+        var_env.add_typedef("T", Ty::Char);
+        let tag = StructRef::new(Some("s".to_owned()));
+        var_env.add_struct_tag("s", tag);
+        assert_eq!(var_env.resolve_typedef("T"), Some(Ty::Char));
+        var_env.leave_block();
+        assert_eq!(var_env.resolve_typedef("T"), Some(Ty::Int));
+        assert_eq!(var_env.resolve_struct_tag("s"), None);
+    }
+
+    #[test]
+    fn inner_block_var_hides_outer_typedef() {
+        let mut var_env = VarEnvironment::new();
+        var_env.enter_function();
+        var_env.add_typedef("T", Ty::Char);
+        var_env.enter_block();
+        var_env.add_enum_constant("T", 3);
+        assert_eq!(var_env.resolve_typedef("T"), None);
+        assert_eq!(var_env.resolve("T"), Some(VarInfo::EnumConst(3)));
+        var_env.enter_block();
+        var_env.add_typedef("T", Ty::Int);
+        assert_eq!(var_env.resolve("T"), None);
+        var_env.leave_block();
+        var_env.leave_block();
+        assert_eq!(var_env.resolve_typedef("T"), Some(Ty::Char));
+    }
+
+    #[test]
+    fn block_local_vars_get_distinct_offsets() {
+        let mut var_env = VarEnvironment::new();
+        var_env.enter_function();
         var_env.add_local_var("x", Ty::Int);
+        var_env.enter_block();
+        assert_eq!(var_env.add_local_var("x", Ty::Int).offset, 24);
+        var_env.leave_block();
         assert_eq!(
             var_env.resolve("x"),
             Some(VarInfo::Local(LocalVarInfo {
@@ -176,4 +440,35 @@ mod test {
             }))
         );
     }
+
+    #[test]
+    fn declared_in_current_scope() {
+        let mut var_env = VarEnvironment::new();
+        var_env.add_global_var("x", Ty::Int, Span::new(4, 1));
+        assert!(var_env.is_declared_in_current_scope("x"));
+        var_env.enter_function();
+        assert!(!var_env.is_declared_in_current_scope("x"));
+        var_env.add_enum_constant("x", 1);
+        assert!(var_env.is_declared_in_current_scope("x"));
+    }
+
+    #[test]
+    fn functions_are_global() {
+        let mut var_env = VarEnvironment::new();
+        var_env.enter_function();
+        var_env.add_function(FunctionInfo {
+            name: "f".to_owned(),
+            return_ty: Ty::Int,
+            param_tys: vec![Ty::Int],
+            is_variadic: false,
+            is_prototyped: true,
+            is_defined: false,
+            span: Span::new(4, 1),
+        });
+        var_env.leave_function();
+        var_env.enter_function();
+        let function_info = var_env.resolve_function("f").unwrap();
+        assert_eq!(function_info.param_tys, vec![Ty::Int]);
+        assert_eq!(var_env.resolve_function("g"), None);
+    }
 }
@@ -1,10 +1,11 @@
+mod line_table;
 mod token;
 mod token_list;
-use std::process::exit;
 
-use crate::error_report::error_at;
+use crate::error_report::{Diagnostic, Span};
 
 pub use self::{
+    line_table::LineTable,
     token::{Token, TokenKind},
     token_list::TokenList,
 };
@@ -12,7 +13,9 @@ pub use self::{
 pub struct Tokenizer<'a> {
     original_input: &'a str,
     input: &'a str,
+    // 入力の先頭からのバイトオフセット
     pos: usize,
+    line_table: LineTable<'a>,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -21,10 +24,18 @@ impl<'a> Tokenizer<'a> {
             original_input: input,
             input,
             pos: 0,
+            line_table: LineTable::new(input),
         }
     }
 
-    pub fn tokenize(mut self) -> TokenList<'a> {
+    pub fn tokenize(mut self) -> Result<TokenList<'a>, Diagnostic> {
+        match self.tokenize_all() {
+            Ok(tokens) => Ok(TokenList::new(self.original_input, tokens, self.line_table)),
+            Err(diagnostic) => Err(self.line_table.annotate(diagnostic)),
+        }
+    }
+
+    fn tokenize_all(&mut self) -> Result<Vec<Token>, Diagnostic> {
         let mut tokens = vec![];
 
         loop {
@@ -34,37 +45,62 @@ impl<'a> Tokenizer<'a> {
                 break;
             }
 
+            let current_position = self.pos;
+
             if self.try_consume("//") {
-                let first_newline = self.input.find(|c| c == '\n').unwrap_or(self.input.len());
+                let first_newline = self.input.find('\n').unwrap_or(self.input.len());
                 let (comment, rest_input) = self.input.split_at(first_newline);
 
-                self.input = &rest_input[1..];
-                self.pos += comment.chars().count();
+                self.input = rest_input;
+                self.pos += comment.len();
                 continue;
             }
 
             if self.try_consume("/*") {
-                let first_newline = self.input.find("*/").unwrap_or(self.input.len());
-                let (comment, rest_input) = self.input.split_at(first_newline);
+                let comment_end = match self.input.find("*/") {
+                    Some(comment_end) => comment_end,
+                    None => {
+                        return Err(Diagnostic::error("Unterminated block comment")
+                            .with_span(Span::new(current_position, 2)));
+                    }
+                };
+                let (comment, rest_input) = self.input.split_at(comment_end);
 
                 self.input = &rest_input[2..];
-                self.pos += comment.chars().count() + 2;
+                self.pos += comment.len() + 2;
                 continue;
             }
 
-            let current_position = self.pos;
-
             let reserved_symbolic_tokens = vec![
+                ("<<=", TokenKind::ShiftLeftAssign),
+                (">>=", TokenKind::ShiftRightAssign),
+                ("<<", TokenKind::ShiftLeft),
+                (">>", TokenKind::ShiftRight),
                 ("<=", TokenKind::LessThanOrEqual),
                 (">=", TokenKind::GreaterThanOrEqual),
                 ("==", TokenKind::Equal),
                 ("!=", TokenKind::NotEqual),
+                ("&&", TokenKind::LogicalAnd),
+                ("||", TokenKind::LogicalOr),
+                ("+=", TokenKind::PlusAssign),
+                ("-=", TokenKind::MinusAssign),
+                ("*=", TokenKind::StarAssign),
+                ("/=", TokenKind::DivAssign),
+                ("%=", TokenKind::ModAssign),
+                ("&=", TokenKind::AmpersandAssign),
+                ("|=", TokenKind::PipeAssign),
+                ("^=", TokenKind::CaretAssign),
+                ("++", TokenKind::Increment),
+                ("--", TokenKind::Decrement),
+                ("->", TokenKind::Arrow),
+                ("...", TokenKind::Ellipsis),
                 (">", TokenKind::GreaterThan),
                 ("<", TokenKind::LessThan),
                 ("+", TokenKind::Plus),
                 ("-", TokenKind::Minus),
                 ("*", TokenKind::Star),
                 ("/", TokenKind::Div),
+                ("%", TokenKind::Mod),
                 ("(", TokenKind::LParen),
                 (")", TokenKind::RParen),
                 ("{", TokenKind::LBrace),
@@ -72,31 +108,49 @@ impl<'a> Tokenizer<'a> {
                 ("[", TokenKind::LBracket),
                 ("]", TokenKind::RBracket),
                 (";", TokenKind::Semicolon),
+                ("?", TokenKind::Question),
+                (":", TokenKind::Colon),
                 ("=", TokenKind::Assign),
                 (",", TokenKind::Comma),
                 ("&", TokenKind::Ampersand),
+                ("!", TokenKind::Not),
+                ("|", TokenKind::Pipe),
+                ("^", TokenKind::Caret),
+                ("~", TokenKind::Tilde),
+                (".", TokenKind::Dot),
             ];
             let consumed_symbolic_token = reserved_symbolic_tokens
                 .into_iter()
                 .find(|(op, _)| self.try_consume(op));
-            if let Some((_, kind)) = consumed_symbolic_token {
-                tokens.push(Token::new_syntax_item(current_position, kind));
+            if let Some((op, kind)) = consumed_symbolic_token {
+                tokens.push(Token::new_syntax_item(current_position, op.len(), kind));
                 continue;
             }
 
-            if let Some(num) = self.try_consume_digits() {
-                tokens.push(Token::new_num(current_position, num));
+            if let Some(num) = self.try_consume_digits()? {
+                tokens.push(Token::new_num(
+                    current_position,
+                    self.pos - current_position,
+                    num,
+                ));
                 continue;
             }
 
             if self.try_consume("\"") {
-                let first_double_quote = self.input.find(|c| c == '"').unwrap_or(self.input.len());
+                let first_double_quote = match self.input.find('"') {
+                    Some(first_double_quote) => first_double_quote,
+                    None => {
+                        return Err(Diagnostic::error("Unterminated string literal")
+                            .with_span(Span::new(current_position, 1)));
+                    }
+                };
                 let (string_contents, rest_input) = self.input.split_at(first_double_quote);
 
                 self.input = &rest_input[1..];
-                self.pos += string_contents.chars().count() + 1;
+                self.pos += string_contents.len() + 1;
                 tokens.push(Token::new_str(
                     current_position,
+                    self.pos - current_position,
                     string_contents.to_string(),
                 ));
                 continue;
@@ -108,26 +162,43 @@ impl<'a> Tokenizer<'a> {
                     ("else", TokenKind::Else),
                     ("return", TokenKind::Return),
                     ("while", TokenKind::While),
+                    ("do", TokenKind::Do),
                     ("for", TokenKind::For),
+                    ("break", TokenKind::Break),
+                    ("continue", TokenKind::Continue),
+                    ("switch", TokenKind::Switch),
+                    ("case", TokenKind::Case),
+                    ("default", TokenKind::Default),
                     ("int", TokenKind::Int),
                     ("char", TokenKind::Char),
+                    ("void", TokenKind::Void),
                     ("sizeof", TokenKind::SizeOf),
+                    ("struct", TokenKind::Struct),
+                    ("union", TokenKind::Union),
+                    ("enum", TokenKind::Enum),
+                    ("typedef", TokenKind::Typedef),
+                    ("extern", TokenKind::Extern),
+                    ("va_start", TokenKind::VaStart),
+                    ("va_arg", TokenKind::VaArg),
+                    ("va_end", TokenKind::VaEnd),
                 ];
                 let consumed_identifier = reserved_identifiers.into_iter().find(|(id, _)| c == *id);
+                let len = self.pos - current_position;
                 if let Some((_, kind)) = consumed_identifier {
-                    tokens.push(Token::new_syntax_item(current_position, kind));
+                    tokens.push(Token::new_syntax_item(current_position, len, kind));
                 } else {
-                    tokens.push(Token::new_ident(current_position, &c));
+                    tokens.push(Token::new_ident(current_position, len, &c));
                 }
                 continue;
             }
 
-            // 単純化のため、トークン化できなかったら即終了させる
-            error_at(self.original_input, current_position, "Unrecognized token");
-            exit(1);
+            // 単純化のため、トークン化できなかったらその場でエラーにする
+            return Err(
+                Diagnostic::error("Unrecognized token").with_span(Span::new(current_position, 1))
+            );
         }
 
-        TokenList::new(self.original_input, tokens)
+        Ok(tokens)
     }
 
     fn skip_whitespace(&mut self) {
@@ -137,34 +208,38 @@ impl<'a> Tokenizer<'a> {
         let spaces = chars
             .take_while_ref(|c| c.is_whitespace())
             .collect::<String>();
-        self.pos += spaces.chars().count();
+        self.pos += spaces.len();
         self.input = chars.as_str();
     }
 
     fn try_consume(&mut self, str: &str) -> bool {
-        if self.input.starts_with(&str) {
-            self.pos += str.chars().count();
-            self.input = &self.input[str.chars().count()..];
+        if self.input.starts_with(str) {
+            self.pos += str.len();
+            self.input = &self.input[str.len()..];
             true
         } else {
             false
         }
     }
 
-    fn try_consume_digits(&mut self) -> Option<i32> {
+    fn try_consume_digits(&mut self) -> Result<Option<i32>, Diagnostic> {
         let first_non_num = self
             .input
-            .find(|c| !char::is_numeric(c))
+            .find(|c: char| !c.is_ascii_digit())
             .unwrap_or(self.input.len());
         let (digit_str, rest_input) = self.input.split_at(first_non_num);
+        if digit_str.is_empty() {
+            return Ok(None);
+        }
 
         match digit_str.parse::<i32>() {
             Ok(num) => {
                 self.input = rest_input;
-                self.pos += digit_str.chars().count();
-                Some(num)
+                self.pos += digit_str.len();
+                Ok(Some(num))
             }
-            Err(_) => None,
+            Err(_) => Err(Diagnostic::error("Number is too large")
+                .with_span(Span::new(self.pos, digit_str.len()))),
         }
     }
 
@@ -174,9 +249,12 @@ impl<'a> Tokenizer<'a> {
             .find(|c| !(char::is_alphanumeric(c) || c == '_'))
             .unwrap_or(self.input.len());
         let (alphabetic_str, rest_input) = self.input.split_at(first_non_alnum_or_underscore);
+        if alphabetic_str.is_empty() {
+            return None;
+        }
 
         self.input = rest_input;
-        self.pos += alphabetic_str.chars().count();
+        self.pos += alphabetic_str.len();
         Some(alphabetic_str.to_owned())
     }
 }
@@ -187,7 +265,7 @@ mod tests {
     #[test]
     fn tokenize_single_digit_num() {
         let expr = "1";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.peek().unwrap().kind, super::TokenKind::Num);
         assert_eq!(token_list.peek().unwrap().num.unwrap(), 1);
         token_list.next();
@@ -197,7 +275,7 @@ mod tests {
     #[test]
     fn tokenize_multiple_digit_num() {
         let expr = "1234";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.peek().unwrap().kind, super::TokenKind::Num);
         assert_eq!(token_list.peek().unwrap().num.unwrap(), 1234);
         token_list.next();
@@ -206,18 +284,19 @@ mod tests {
 
     #[test]
     fn tokenize_operators() {
-        let expr = "+-*/";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let expr = "+-*/%";
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Plus);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Minus);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Star);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Div);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Mod);
     }
 
     #[test]
     fn tokenize_parens() {
         let expr = "()";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::LParen);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::RParen);
     }
@@ -225,7 +304,7 @@ mod tests {
     #[test]
     fn skip_whitespaces() {
         let expr = "5 + 20 - 4";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Num);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Plus);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Num);
@@ -236,7 +315,7 @@ mod tests {
     #[test]
     fn tokenize_multi_length_operators() {
         let expr = "<=>===!=<>";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(
             token_list.next().unwrap().kind,
             super::TokenKind::LessThanOrEqual
@@ -257,7 +336,7 @@ mod tests {
     #[test]
     fn tokenize_single_char_ident() {
         let expr = "a b";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
     }
@@ -265,7 +344,7 @@ mod tests {
     #[test]
     fn tokenize_single_multi_char_ident() {
         let expr = "foo bar";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         let first_ident = token_list.next().unwrap();
         let second_ident = token_list.next().unwrap();
         assert_eq!(first_ident.kind, super::TokenKind::Ident);
@@ -277,14 +356,14 @@ mod tests {
     #[test]
     fn tokenize_semicolon() {
         let expr = ";";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Semicolon);
     }
 
     #[test]
     fn tokenize_equal_assign() {
         let expr = "===";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Equal);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Assign);
     }
@@ -292,7 +371,7 @@ mod tests {
     #[test]
     fn tokenize_program() {
         let expr = "a = 42; a;";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         // a = 42;
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Assign);
@@ -306,7 +385,7 @@ mod tests {
     #[test]
     fn tokenize_return() {
         let expr = "return 42;";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Return);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Num);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Semicolon);
@@ -315,7 +394,7 @@ mod tests {
     #[test]
     fn correctly_tokenize_return_like_local_var() {
         let expr = "return_with_suffix prefixed_return return42 return";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
@@ -325,7 +404,7 @@ mod tests {
     #[test]
     fn tokenize_if() {
         let expr = "prefixed_if if_with_suffix if";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::If);
@@ -334,7 +413,7 @@ mod tests {
     #[test]
     fn tokenize_else() {
         let expr = "prefixed_else else_with_suffix else";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Else);
@@ -343,7 +422,7 @@ mod tests {
     #[test]
     fn tokenize_while() {
         let expr = "prefixed_while while_with_suffix while";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::While);
@@ -352,7 +431,7 @@ mod tests {
     #[test]
     fn tokenize_for() {
         let expr = "prefixed_for for_with_suffix for";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::For);
@@ -361,7 +440,7 @@ mod tests {
     #[test]
     fn tokenize_brace() {
         let expr = "{}";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::LBrace);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::RBrace);
     }
@@ -369,35 +448,35 @@ mod tests {
     #[test]
     fn tokenize_comma() {
         let expr = ",";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Comma);
     }
 
     #[test]
     fn tokenize_ampersand() {
         let expr = "&";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ampersand);
     }
 
     #[test]
     fn tokenize_int() {
         let expr = "int";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Int);
     }
 
     #[test]
     fn tokenize_sizeof() {
         let expr = "sizeof";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::SizeOf);
     }
 
     #[test]
     fn tokenize_bracket() {
         let expr = "[]";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::LBracket);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::RBracket);
     }
@@ -405,7 +484,7 @@ mod tests {
     #[test]
     fn tokenize_char() {
         let expr = "prefixed_char char_with_suffix char";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
         assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Char);
@@ -414,10 +493,142 @@ mod tests {
     #[test]
     fn tokenize_string() {
         let expr = "\"hello, world\"";
-        let mut token_list = super::Tokenizer::new(expr).tokenize();
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
         let next_token = token_list.next().unwrap();
         assert_eq!(next_token.kind, super::TokenKind::String);
         assert_eq!(next_token.str.unwrap(), "hello, world");
         assert!(token_list.next().is_none());
     }
+
+    #[test]
+    fn tokenize_logical_operators() {
+        let expr = "!a&&b||c!=d&e";
+        let kinds = super::Tokenizer::new(expr)
+            .tokenize()
+            .unwrap()
+            .map(|token| token.kind)
+            .collect::<Vec<_>>();
+        use super::TokenKind::*;
+        assert_eq!(
+            kinds,
+            vec![
+                Not, Ident, LogicalAnd, Ident, LogicalOr, Ident, NotEqual, Ident, Ampersand, Ident
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_bitwise_operators() {
+        let expr = "a<<b>>c<=d|e^~f";
+        let kinds = super::Tokenizer::new(expr)
+            .tokenize()
+            .unwrap()
+            .map(|token| token.kind)
+            .collect::<Vec<_>>();
+        use super::TokenKind::*;
+        assert_eq!(
+            kinds,
+            vec![
+                Ident,
+                ShiftLeft,
+                Ident,
+                ShiftRight,
+                Ident,
+                LessThanOrEqual,
+                Ident,
+                Pipe,
+                Ident,
+                Caret,
+                Tilde,
+                Ident
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_assignment_operators() {
+        let expr = "a<<=b>>=c+=d++-=--e";
+        let kinds = super::Tokenizer::new(expr)
+            .tokenize()
+            .unwrap()
+            .map(|token| token.kind)
+            .collect::<Vec<_>>();
+        use super::TokenKind::*;
+        assert_eq!(
+            kinds,
+            vec![
+                Ident,
+                ShiftLeftAssign,
+                Ident,
+                ShiftRightAssign,
+                Ident,
+                PlusAssign,
+                Ident,
+                Increment,
+                MinusAssign,
+                Decrement,
+                Ident
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_conditional_operator() {
+        let expr = "a?b:c";
+        let kinds = super::Tokenizer::new(expr)
+            .tokenize()
+            .unwrap()
+            .map(|token| token.kind)
+            .collect::<Vec<_>>();
+        use super::TokenKind::*;
+        assert_eq!(kinds, vec![Ident, Question, Ident, Colon, Ident]);
+    }
+
+    #[test]
+    fn tokenize_type_keywords() {
+        let expr = "union enum typedef typedefs";
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Union);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Enum);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Typedef);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
+    }
+
+    #[test]
+    fn tokenize_struct_member_access() {
+        let expr = "struct s a.b->c-d";
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Struct);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Dot);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Arrow);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Minus);
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
+    }
+
+    #[test]
+    fn unrecognized_token_is_error() {
+        let expr = "a @ b";
+        let err = super::Tokenizer::new(expr).tokenize().unwrap_err();
+        assert_eq!(err.message, "Unrecognized token");
+        assert_eq!(err.span.unwrap().start, 2);
+    }
+
+    #[test]
+    fn unterminated_string_is_error() {
+        let expr = "\"hello";
+        let err = super::Tokenizer::new(expr).tokenize().unwrap_err();
+        assert_eq!(err.message, "Unterminated string literal");
+    }
+
+    #[test]
+    fn line_comment_at_end_of_input() {
+        let expr = "a // comment";
+        let mut token_list = super::Tokenizer::new(expr).tokenize().unwrap();
+        assert_eq!(token_list.next().unwrap().kind, super::TokenKind::Ident);
+        assert!(token_list.next().is_none());
+    }
 }
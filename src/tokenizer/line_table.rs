@@ -0,0 +1,74 @@
+use crate::error_report::{Diagnostic, Location};
+
+// 各行の先頭のバイトオフセットを保持し、バイトオフセットから行番号と列番号を求める
+#[derive(Debug)]
+pub struct LineTable<'a> {
+    input: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineTable<'a> {
+    pub fn new(input: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(input.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { input, line_starts }
+    }
+
+    // 行番号と列番号は1始まり。列は文字単位で数える
+    pub fn locate(&self, offset: usize) -> Location {
+        let offset = offset.min(self.input.len());
+        let line_index = match self.line_starts.binary_search(&offset) {
+            Ok(line_index) => line_index,
+            Err(next_line_index) => next_line_index - 1,
+        };
+        let line_start = self.line_starts[line_index];
+        let line_end = self
+            .line_starts
+            .get(line_index + 1)
+            .map(|next_line_start| next_line_start - 1)
+            .unwrap_or(self.input.len());
+
+        Location {
+            line: line_index + 1,
+            column: self.input[line_start..offset].chars().count() + 1,
+            source_line: self.input[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_owned(),
+        }
+    }
+
+    // spanを持つ診断に位置情報を付ける
+    pub fn annotate(&self, diagnostic: Diagnostic) -> Diagnostic {
+        match (diagnostic.span, &diagnostic.location) {
+            (Some(span), None) => {
+                let location = self.locate(span.start);
+                diagnostic.with_location(location)
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineTable;
+
+    #[test]
+    fn locate_line_and_column() {
+        let line_table = LineTable::new("int main() {\n  return x;\n}\n");
+        let location = line_table.locate(22);
+        assert_eq!(location.line, 2);
+        assert_eq!(location.column, 10);
+        assert_eq!(location.source_line, "  return x;");
+    }
+
+    #[test]
+    fn column_counts_characters() {
+        let input = "\"あいう\" @";
+        let line_table = LineTable::new(input);
+        let location = line_table.locate(input.find('@').unwrap());
+        assert_eq!(location.line, 1);
+        assert_eq!(location.column, 7);
+    }
+}
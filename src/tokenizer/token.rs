@@ -4,6 +4,7 @@ pub enum TokenKind {
     Minus,
     Star,
     Div,
+    Mod,
     Num,
     Ident,
     LParen,
@@ -20,62 +21,114 @@ pub enum TokenKind {
     Equal,
     NotEqual,
     Semicolon,
+    Question,
+    Colon,
     Return,
     If,
     Else,
     While,
+    Do,
     For,
+    Break,
+    Continue,
+    Switch,
+    Case,
+    Default,
     Comma,
     Ampersand,
+    LogicalAnd,
+    LogicalOr,
+    Not,
+    Pipe,
+    Caret,
+    Tilde,
+    ShiftLeft,
+    ShiftRight,
+    // 複合代入演算子
+    PlusAssign,
+    MinusAssign,
+    StarAssign,
+    DivAssign,
+    ModAssign,
+    AmpersandAssign,
+    PipeAssign,
+    CaretAssign,
+    ShiftLeftAssign,
+    ShiftRightAssign,
+    Increment,
+    Decrement,
     SizeOf,
     String,
+    Dot,
+    Arrow,
+    Ellipsis,
+    Extern,
+    VaStart,
+    VaArg,
+    VaEnd,
     // Type like
     Int,
     Char,
+    Void,
+    Struct,
+    Union,
+    Enum,
+    Typedef,
 }
 
+use crate::error_report::Span;
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Token {
     pub position: usize,
+    pub len: usize,
     pub kind: TokenKind,
     pub num: Option<i32>,    // Number
     pub str: Option<String>, // Identifier
 }
 
 impl Token {
-    pub fn new_syntax_item(position: usize, kind: TokenKind) -> Self {
+    pub fn new_syntax_item(position: usize, len: usize, kind: TokenKind) -> Self {
         Self {
             position,
+            len,
             kind,
             num: None,
             str: None,
         }
     }
 
-    pub fn new_num(position: usize, num: i32) -> Self {
+    pub fn new_num(position: usize, len: usize, num: i32) -> Self {
         Self {
             position,
+            len,
             kind: TokenKind::Num,
             num: Some(num),
             str: None,
         }
     }
 
-    pub fn new_ident(position: usize, str: &str) -> Self {
+    pub fn new_ident(position: usize, len: usize, str: &str) -> Self {
         Self {
             position,
+            len,
             kind: TokenKind::Ident,
             num: None,
             str: Some(str.to_string()),
         }
     }
 
-    pub fn new_str(position: usize, str: String) -> Self {
+    pub fn new_str(position: usize, len: usize, str: String) -> Self {
         Self {
             position,
+            len,
             kind: TokenKind::String,
             num: None,
             str: Some(str),
         }
     }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.position, self.len)
+    }
 }
@@ -1,26 +1,22 @@
-use std::process::exit;
+use crate::error_report::{Diagnostic, Span};
 
-use crate::error_report::error_at;
-
-use super::token::{Token, TokenKind};
+use super::{
+    line_table::LineTable,
+    token::{Token, TokenKind},
+};
 
 #[derive(Debug)]
 pub struct TokenList<'a> {
     original_input: &'a str,
     tokens: Vec<Token>,
     pos: usize,
+    line_table: LineTable<'a>,
 }
 
-impl<'a> TokenList<'a> {
-    pub fn new(original_input: &'a str, tokens: Vec<Token>) -> Self {
-        Self {
-            original_input,
-            tokens,
-            pos: 0,
-        }
-    }
+impl<'a> Iterator for TokenList<'a> {
+    type Item = Token;
 
-    pub fn next(&mut self) -> Option<Token> {
+    fn next(&mut self) -> Option<Token> {
         if self.pos >= self.tokens.len() {
             None
         } else {
@@ -29,6 +25,21 @@ impl<'a> TokenList<'a> {
             Some(token)
         }
     }
+}
+
+impl<'a> TokenList<'a> {
+    pub fn new(original_input: &'a str, tokens: Vec<Token>, line_table: LineTable<'a>) -> Self {
+        Self {
+            original_input,
+            tokens,
+            pos: 0,
+            line_table,
+        }
+    }
+
+    pub fn line_table(&self) -> &LineTable<'a> {
+        &self.line_table
+    }
 
     pub fn at_end(&self) -> bool {
         self.pos >= self.tokens.len()
@@ -42,6 +53,19 @@ impl<'a> TokenList<'a> {
         }
     }
 
+    // n個先のトークンをのぞく。peek_nth(0)はpeek()と同じ
+    pub fn peek_nth(&self, n: usize) -> Option<Token> {
+        self.tokens.get(self.pos + n).cloned()
+    }
+
+    // 次のトークンの位置。入力の終わりに達していれば入力の末尾を指す
+    pub fn current_span(&self) -> Span {
+        match self.tokens.get(self.pos) {
+            Some(token) => token.span(),
+            None => self.eof_span(),
+        }
+    }
+
     pub fn try_consume(&mut self, kind: &TokenKind) -> Option<Token> {
         let next = self.peek();
         if let Some(next) = next {
@@ -55,65 +79,56 @@ impl<'a> TokenList<'a> {
         }
     }
 
-    pub fn expect_kind(&mut self, kind: &TokenKind) -> Token {
+    pub fn expect_kind(&mut self, kind: &TokenKind) -> Result<Token, Diagnostic> {
         let next = self.peek();
         if let Some(next) = next {
             if next.kind == *kind {
                 self.advance();
-                next
+                Ok(next)
             } else {
-                self.exit_with_unexpected_token(next.position, &format!("Expected {:?}", kind));
+                Err(self.unexpected_token(&next, &format!("Expected {:?}", kind)))
             }
         } else {
-            self.exit_with_unexpected_eof(&format!("Expected {:?}", kind));
+            Err(self.unexpected_eof(&format!("Expected {:?}", kind)))
         }
     }
 
-    pub fn expect_num(&mut self) -> i32 {
+    pub fn expect_num(&mut self) -> Result<i32, Diagnostic> {
         let next_token = self.peek();
         match next_token {
             Some(token) => {
                 if token.kind == TokenKind::Num {
                     self.advance();
-                    token.num.unwrap()
+                    Ok(token.num.unwrap())
                 } else {
-                    self.exit_with_unexpected_token(token.position, "Expected number");
+                    Err(self.unexpected_token(&token, "Expected number"))
                 }
             }
-            None => {
-                self.exit_with_unexpected_eof("Expected number");
-            }
+            None => Err(self.unexpected_eof("Expected number")),
         }
     }
 
-    fn exit_with_unexpected_token(&self, position: usize, additional_message: &str) -> ! {
+    fn unexpected_token(&self, token: &Token, additional_message: &str) -> Diagnostic {
         if additional_message.is_empty() {
-            error_at(self.original_input, position, "Unexpected Token");
+            Diagnostic::error("Unexpected Token").with_span(token.span())
         } else {
-            error_at(
-                self.original_input,
-                position,
-                &format!("Unexpected Token, {}", additional_message),
-            );
+            Diagnostic::error(format!("Unexpected Token, {}", additional_message))
+                .with_span(token.span())
         }
-        exit(1)
     }
 
-    fn exit_with_unexpected_eof(&self, additional_message: &str) -> ! {
+    fn unexpected_eof(&self, additional_message: &str) -> Diagnostic {
         if additional_message.is_empty() {
-            error_at(
-                self.original_input,
-                self.original_input.len(),
-                "Unexpected EOF",
-            );
+            Diagnostic::error("Unexpected EOF").with_span(self.eof_span())
         } else {
-            error_at(
-                self.original_input,
-                self.original_input.len(),
-                &format!("Unexpected EOF, {}", additional_message),
-            );
+            Diagnostic::error(format!("Unexpected EOF, {}", additional_message))
+                .with_span(self.eof_span())
         }
-        exit(1)
+    }
+
+    // 末尾の空白や改行の後ろではなく、最後の文字の直後を指す
+    fn eof_span(&self) -> Span {
+        Span::new(self.original_input.trim_end().len(), 0)
     }
 
     fn advance(&mut self) {
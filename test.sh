@@ -1,8 +1,34 @@
 #!/bin/bash
-cat <<EOF | clang -xc -c -o tmp2.o -
+# TARGET=aarch64-linux-gnu ./test.sh でLinux向けのELFを生成し、qemu-userで実行する
+TARGET="${TARGET:-aarch64-apple-darwin}"
+case "$TARGET" in
+  aarch64-apple-darwin)
+    CC="${CC:-cc}"
+    RUN=""
+    ;;
+  aarch64-linux-gnu)
+    CC="${CC:-aarch64-linux-gnu-gcc}"
+    RUN="${RUN:-qemu-aarch64 -L /usr/aarch64-linux-gnu}"
+    ;;
+  x86_64-linux-gnu)
+    CC="${CC:-cc}"
+    RUN=""
+    ;;
+  *)
+    echo "unsupported target: $TARGET"
+    exit 1
+    ;;
+esac
+
+cat <<EOF | $CC -xc -c -o tmp2.o -
 #include <stdio.h>
 #include <stdlib.h>
 int add(int x, int y) { return x+y; }
+int ext_var = 7;
+char ret_minus1(void) { return -1; }
+int sum10(int a, int b, int c, int d, int e, int f, int g, int h, char i, int j) {
+    return a*1 + b*2 + c*3 + d*4 + e*5 + f*6 + g*7 + h*8 + i*9 + j*10;
+}
 void alloc4(int **p, int a, int b, int c, int d) {
     int *int_ptr = (int *)malloc(sizeof(int) * 4);
     int_ptr[0] = a;
@@ -19,10 +45,10 @@ assert() {
   input="$2"
 
   echo "$input" > tmp.c
-  RUST_BACKTRACE=1 cargo run -- tmp.c > tmp.s
-  cc -c tmp.s
-  cc -o tmp tmp.o tmp2.o
-  ./tmp
+  RUST_BACKTRACE=1 cargo run -- --target "$TARGET" tmp.c > tmp.s
+  $CC -c tmp.s
+  $CC -o tmp tmp.o tmp2.o
+  $RUN ./tmp
   actual="$?"
 
   if [ "$actual" = "$expected" ]; then
@@ -149,4 +175,261 @@ assert 3 'int main() { char x[3]; x[0] = -1; x[1] = -2; int y; y = 4; return x[0
 assert 42 'int main() { char *c; c = "hello, world\n"; printf(c); return 42; }' 
 assert 42 'int main() { printf("hello, world\n"); return 42; }' 
 
+# subscript with expressions
+assert 7 'int main() { int x[4]; int i; for (i = 0; i < 4; i = i + 1) x[i] = i * 2 + 1; return x[3]; }'
+assert 6 'int main() { int x[2][3]; int i; i = 1; x[i][i + 1] = 6; return x[1][2]; }'
+assert 3 'int main() { char *s; s = "abc"; return s[2] - s[0] + 1; }'
+
+# struct
+assert 8 'int main() { struct { char a; int b; } x; return sizeof(x); }'
+assert 16 'int main() { struct { char a; int *b; } x; return sizeof(x); }'
+assert 3 'int main() { struct { int a; int b; } x; x.a = 1; x.b = 2; return x.a + x.b; }'
+assert 5 'int main() { struct { char a; int b; char c; } x; x.a = 1; x.b = 2; x.c = 3; return x.b + x.c; }'
+assert 6 'int main() { struct s { int a; int b; }; struct s x; struct s *p; p = &x; p->a = 2; p->b = 4; return x.a + x.b; }'
+assert 9 'int main() { struct { int a; struct { char b; int c; } in; } x; x.in.c = 9; x.a = 1; return x.in.c; }'
+assert 12 'int main() { struct { int a[3]; } x; x.a[0] = 4; x.a[2] = 8; return x.a[0] + x.a[2]; }'
+assert 7 'int main() { struct { int a; int b; } x[3]; x[2].b = 7; return x[2].b; }'
+assert 3 'struct node { int val; struct node *next; }; int main() { struct node a; struct node b; a.val = 1; b.val = 2; a.next = &b; return a.val + a.next->val; }'
+assert 42 'struct point { int x; int y; } g; int main() { g.y = 42; return g.y; }'
+assert 5 'int main() { struct { int a; int b; } x; struct { int a; int b; } *p; x.a = 5; p = &x; return p->a; }'
+assert 11 'int main() { struct s { char c; int a; } x; struct s y; x.c = 1; x.a = 10; y = x; return y.c + y.a; }'
+assert 37 'struct s { int a; int b; }; int main() { struct s x; struct s y; int c; y.a = 3; y.b = 4; c = 1; return (x = y).a + (1, y).b + (c ? x : y).a * 10; }'
+assert 1 'struct s { int a; int b; }; int main() { struct s { char c; }; return sizeof(struct s); }'
+assert 8 'struct s { int a; int b; }; int main() { { struct s { char c; }; } return sizeof(struct s); }'
+assert 4 'int main() { struct s { int a; }; { union s { char c[3]; int i; } x; return sizeof(x); } }'
+
+# union
+assert 8 'int main() { union { int a; char b[5]; } x; return sizeof(x); }'
+assert 4 'int main() { union { int a; char b; } x; return sizeof(x); }'
+assert 3 'int main() { union { int a; char b; } x; x.a = 515; return x.b; }'
+assert 42 'int main() { union u { int a; int *p; }; union u x; union u *p; p = &x; p->a = 42; return x.a; }'
+assert 7 'struct var { int kind; union { int i; char c; } as; }; int main() { struct var v; v.kind = 1; v.as.i = 6; return v.kind + v.as.i; }'
+assert 9 'int main() { union { int a; char b; } x; union { int a; char b; } *p; p = &x; x.a = 9; return p->a; }'
+assert 5 'int main() { union u { char c; int i; } x; union u y; x.i = 5; y = x; return y.i; }'
+
+# enum
+assert 0 'int main() { enum { RED, GREEN, BLUE }; return RED; }'
+assert 2 'int main() { enum { RED, GREEN, BLUE }; return BLUE; }'
+assert 7 'int main() { enum Color { RED, GREEN = 5, BLUE, }; return BLUE + GREEN - 4; }'
+assert 3 'enum Color { RED = 1, GREEN = RED + 1 }; int main() { enum Color c; c = GREEN; return c + RED; }'
+assert 4 'int main() { enum { N = 2 }; int a[N * 2]; return sizeof(a) / sizeof(a[0]); }'
+assert 4 'int main() { enum e { A = 4 } x; x = A; return x; }'
+assert 1 'enum { A = 1 }; int main() { int A; A = 3; enum { B = 1 }; return B; }'
+assert 9 'int main() { int RED; RED = 2; { enum { RED = 9 }; return RED; } }'
+assert 2 'int main() { int RED; RED = 2; { enum { RED = 9 }; } return RED; }'
+
+# typedef
+assert 3 'typedef int MyInt; int main() { MyInt x; x = 3; return x; }'
+assert 8 'typedef int *IntPtr; int main() { IntPtr p; return sizeof(p); }'
+assert 5 'typedef int *IntPtr; int main() { int x; IntPtr p; p = &x; *p = 5; return x; }'
+assert 12 'typedef struct { int x; int y; } Point; int main() { Point p; p.x = 5; p.y = 7; return p.x + p.y; }'
+assert 3 'typedef struct node Node; struct node { int val; Node *next; }; int main() { Node a; Node b; a.next = &b; b.val = 3; return a.next->val; }'
+assert 2 'typedef int T; int main() { int T; T = 2; return T; }'
+assert 4 'int main() { typedef char Buf[4]; Buf b; return sizeof(b); }'
+assert 4 'typedef int T; int main() { { typedef char T; } T x; return sizeof(x); }'
+assert 1 'int main() { typedef int T; { int T; T = 1; return T; } }'
+assert 8 'int main() { typedef char T; { typedef int *T; { T x; return sizeof(x); } } }'
+assert 5 'int main() { int x; x = 5; { int x; x = 3; } return x; }'
+assert 6 'typedef int A, *B; int main() { A a; B b; a = 6; b = &a; return *b; }'
+
+# cast
+assert 8 'typedef int *IntPtr; int main() { return sizeof(IntPtr); }'
+assert 24 'int main() { return sizeof(int[6]); }'
+assert 1 'int main() { return (char)257; }'
+assert 44 'int main() { int x; x = 300; return (char)x; }'
+assert 255 'int main() { return (char)-1 + 256; }'
+assert 3 'int main() { int x[2]; x[1] = 3; return *(int *)((char *)x + 4); }'
+assert 7 'typedef int MyInt; int main() { return (MyInt)7; }'
+
+# prototype / extern
+assert 3 'int add(int x, int y); int main() { return add(1, 2); }'
+assert 5 'int sub(int, int); int main() { return sub(8, 3); } int sub(int a, int b) { return a - b; }'
+assert 55 'int fib(int n); int main() { return fib(10); } int fib(int n) { if (n <= 1) return n; return fib(n - 1) + fib(n - 2); }'
+assert 7 'extern int ext_var; int main() { return ext_var; }'
+assert 9 'extern int ext_var; int main() { ext_var = 9; return ext_var; }'
+assert 4 'extern int counter; int main() { counter = 4; return counter; } int counter;'
+assert 8 'int printf(char *fmt, ...); int main() { printf("prototype\n"); return 8; }'
+assert 3 'int plus(); int main() { return plus(1, 2); } int plus(int a, int b) { return a + b; }'
+assert 6 'int twice(); int twice(int a) { return a * 2; } int main() { return twice(3); }'
+
+# return type
+assert 44 'char f() { return 300; } int main() { return f(); }'
+assert 1 'char ret_minus1(void); int main() { return ret_minus1() + 2; }'
+assert 1 'char f(); int main() { return sizeof(f()); } char f() { return 1; }'
+assert 5 'int g[3]; int *f() { return g; } int main() { g[2] = 5; return *(f() + 2); }'
+assert 8 'int g[3]; int *f() { return g; } int main() { return sizeof(f()); }'
+assert 6 'char g[4]; void *f() { return g; } int main() { char *c; g[2] = 6; c = f(); return c[2]; }'
+assert 3 'int g; void set() { g = 3; return; } int main() { set(); return g; }'
+assert 1 'void nop(void) {} int main() { nop(); return 1; }'
+assert 2 'int f(void) { char c; c = 2; return c; } int main() { return f(); }'
+
+# parameter types
+assert 5 'int len(char *s, int n) { int i; i = 0; while (s[i]) i = i + 1; return i + n; } int main() { return len("abc", 2); }'
+assert 3 'int f(char c) { return c; } int main() { return f(259); }'
+assert 2 'char f(char a, char b) { return a - b; } int main() { return f(5, 3); }'
+assert 7 'void set(int *p, int v) { *p = v; } int main() { int x; set(&x, 7); return x; }'
+assert 6 'int sum(int a[], int n) { int i; int s; s = 0; for (i = 0; i < n; i = i + 1) s = s + a[i]; return s; } int main() { int a[3]; a[0] = 1; a[1] = 2; a[2] = 3; return sum(a, 3); }'
+assert 8 'int f(int a[4]) { return sizeof(a); } int main() { int a[4]; return f(a); }'
+assert 9 'int f(int m[][3]) { return m[1][2]; } int main() { int m[2][3]; m[1][2] = 9; return f(m); }'
+assert 4 'struct p { int x; int y; }; int f(struct p *p) { return p->y; } int main() { struct p v; v.y = 4; return f(&v); }'
+assert 11 'int f(int a, char b, int *c, char d) { return a + b + *c + d; } int main() { int x; x = 5; return f(1, 2, &x, 3); }'
+
+# stack arguments
+assert 129 'int sum10(int a, int b, int c, int d, int e, int f, int g, int h, char i, int j); int main() { return sum10(1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }'
+assert 130 'int sum10(int a, int b, int c, int d, int e, int f, int g, int h, char i, int j); int main() { return 1 + sum10(1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }'
+assert 129 'int f(int a, int b, int c, int d, int e, int f, int g, int h, char i, int j) { return a*1 + b*2 + c*3 + d*4 + e*5 + f*6 + g*7 + h*8 + i*9 + j*10; } int main() { return f(1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }'
+assert 117 'int f(int a0, int a1, int a2, int a3, int a4, int a5, int a6, int a7, int a8, int a9, int a10, int a11, int a12, int a13, int a14, int a15, int a16, int a17, int a18, int a19, int a20, int a21, int a22, int a23, int a24, int a25, int a26, int a27, int a28, int a29, int a30, int a31, int a32, int a33, int a34, int a35, int a36, int a37, int a38, int a39, int a40, int a41, int a42, int a43, int a44, int a45, int a46, int a47, int a48, int a49, int a50, int a51, int a52, int a53, int a54, int a55, int a56, int a57, int a58, int a59, int a60, int a61, int a62, int a63, int a64, int a65, int a66, int a67, int a68, int a69) { char buf[5000]; buf[4999] = a69; return buf[4999] + a8 + a40 + a0; } int main() { return f(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69); }'
+assert 7 'int f(int a, int b, int c, int d, int e, int f, int g) { return g; } int main() { return f(1, 2, 3, 4, 5, 6, 7); }'
+assert 12 'int f(int a, int b, int c, int d, int e, int f, int g, int h, int *p, char c2) { return *p + c2; } int main() { int x; x = 5; return f(0, 0, 0, 0, 0, 0, 0, 0, &x, 7); }'
+assert 36 'int f(int a, int b, int c, int d, int e, int f, int g, int h, int i) { return a + b + c + d + e + f + g + h + i - 9; } int main() { return f(1, 2, 3, 4, 5, 6, 7, 8, f(1, 1, 1, 1, 1, 1, 1, 1, 10)); }'
+assert 0 'int main() { printf("%d %d %d %d %d %d %d %d\n", 1, 2, 3, 4, 5, 6, 7, 8); return 0; }'
+
+# variadic functions
+assert 6 'int sum(int n, ...) { va_list ap; int s; int i; va_start(ap, n); s = 0; for (i = 0; i < n; i = i + 1) s = s + va_arg(ap, int); va_end(ap); return s; } int main() { return sum(3, 1, 2, 3); }'
+assert 55 'int sum(int n, ...) { va_list ap; int s; int i; va_start(ap, n); s = 0; for (i = 0; i < n; i = i + 1) s = s + va_arg(ap, int); va_end(ap); return s; } int main() { return sum(10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }'
+assert 15 'int f(int a, int b, int c, int d, int e, int f, int g, int h, int i, ...) { va_list ap; va_start(ap, i); return i + va_arg(ap, int) - 4; } int main() { return f(1, 2, 3, 4, 5, 6, 7, 8, 9, 10); }'
+assert 3 'char *pick(int n, ...) { va_list ap; char *s; va_start(ap, n); while (n) { s = va_arg(ap, char *); n = n - 1; } va_end(ap); return s; } int main() { return *pick(2, "a", "\003"); }'
+assert 9 'int vsum(int n, va_list ap) { int s; s = 0; while (n) { s = s + va_arg(ap, int); n = n - 1; } return s; } int sum(int n, ...) { va_list ap; int r; va_start(ap, n); r = vsum(n, ap); va_end(ap); return r; } int main() { return sum(2, 4, 5); }'
+assert 5 'int vsprintf(char *buf, char *fmt, va_list ap); char buf[64]; void format(char *fmt, ...) { va_list ap; va_start(ap, fmt); vsprintf(buf, fmt, ap); va_end(ap); } int main() { format("%d-%d", 12, 34); return buf[1] - 48 + buf[4] - 48 - 1; }'
+assert 0 'int printf(char *fmt, ...); int main() { printf("%d %s %d\n", 1, "two", 3); return 0; }'
+
+# logical operators
+assert 1 'int main() { return 1 && 2; }'
+assert 0 'int main() { return 1 && 0; }'
+assert 0 'int main() { return 0 && 1; }'
+assert 1 'int main() { return 0 || 3; }'
+assert 0 'int main() { return 0 || 0; }'
+assert 1 'int main() { return !0; }'
+assert 0 'int main() { return !5; }'
+assert 1 'int main() { return !!7; }'
+assert 1 'int main() { int *p; p = 0; return !p; }'
+assert 1 'int main() { return 1 || 0 && 0; }'
+assert 1 'int main() { return 2 == 2 && 3 != 4; }'
+assert 0 'int g; int inc() { g = g + 1; return 1; } int main() { 0 && inc(); 1 || inc(); return g; }'
+assert 2 'int g; int inc() { g = g + 1; return 1; } int main() { 1 && inc(); 0 || inc(); return g; }'
+assert 3 'int main() { int a[3]; int i; a[0] = 1; a[1] = 1; a[2] = 0; i = 0; while (i < 5 && a[i]) i = i + 1; return i + 1; }'
+assert 7 'int main() { return 6 + (1 && 1); }'
+
+# bitwise operators
+assert 2 'int main() { return 6 & 3; }'
+assert 7 'int main() { return 6 | 3; }'
+assert 5 'int main() { return 6 ^ 3; }'
+assert 1 'int main() { return ~-2; }'
+assert 0 'int main() { return ~0 + 1; }'
+assert 40 'int main() { return 5 << 3; }'
+assert 5 'int main() { return 40 >> 3; }'
+assert 255 'int main() { return -1 >> 8; }'
+assert 1 'int main() { return 1 | 2 & 0; }'
+assert 6 'int main() { return 1 << 1 + 1 | 2; }'
+assert 1 'int main() { return 5 & 1 == 1; }'
+assert 3 'int main() { char c; c = 7; return c & ~4 ^ 0; }'
+assert 133 'int main() { int h; int i; char *s; s = "abc"; h = 5381; i = 0; while (s[i]) { h = (h << 5) + h ^ s[i]; i = i + 1; } return h & 255; }'
+assert 1 'int main() { int x; x = 3; return &x != 0 && (x & 1); }'
+assert 12 'int main() { int a[1 << 2 | ~-9]; return sizeof(a) / 4; }'
+
+# modulo
+assert 2 'int main() { return 17 % 5; }'
+assert 0 'int main() { return 15 % 5; }'
+assert 3 'int main() { return 3 % 7; }'
+assert 2 'int main() { return -17 % 5 + 4; }'
+assert 2 'int main() { return 17 % -5; }'
+assert 1 'int main() { return -17 / 5 * 5 + -17 % 5 == -17; }'
+assert 4 'int main() { return 2 * 7 % 5 + 0; }'
+assert 5 'int main() { return 1 + 9 % 5; }'
+assert 3 'int main() { int a[10 % 7]; return sizeof(a) / 4; }'
+assert 4 'int main() { int i; int n; n = 0; i = 0; while (i < 12) { if (i % 3 == 0) n = n + 1; i = i + 1; } return n; }'
+
+# compound assignment and increment/decrement
+assert 7 'int main() { int a; a = 5; a += 2; return a; }'
+assert 3 'int main() { int a; a = 5; a -= 2; return a; }'
+assert 15 'int main() { int a; a = 5; a *= 3; return a; }'
+assert 2 'int main() { int a; a = 5; a /= 2; return a; }'
+assert 1 'int main() { int a; a = 5; a %= 2; return a; }'
+assert 4 'int main() { int a; a = 6; a &= 5; return a; }'
+assert 7 'int main() { int a; a = 6; a |= 3; return a; }'
+assert 5 'int main() { int a; a = 6; a ^= 3; return a; }'
+assert 40 'int main() { int a; a = 5; a <<= 3; return a; }'
+assert 5 'int main() { int a; a = 40; a >>= 3; return a; }'
+assert 10 'int main() { int a; int b; a = 2; b = a += 3; return a + b; }'
+assert 6 'int main() { int a; a = 5; return ++a; }'
+assert 4 'int main() { int a; a = 5; return --a; }'
+assert 5 'int main() { int a; a = 5; return a++; }'
+assert 6 'int main() { int a; a = 5; a++; return a; }'
+assert 5 'int main() { int a; a = 5; return a--; }'
+assert 4 'int main() { int a; a = 5; a--; return a; }'
+assert 3 'int main() { int a[3]; int *p; a[0] = 1; a[1] = 2; a[2] = 3; p = a; p += 2; return *p; }'
+assert 1 'int main() { int a[3]; int *p; a[0] = 1; a[1] = 2; a[2] = 3; p = a + 2; p -= 2; return *p; }'
+assert 2 'int main() { int a[3]; int *p; a[0] = 1; a[1] = 2; a[2] = 3; p = a; p++; return *p; }'
+assert 1 'int main() { int a[3]; int *p; a[0] = 1; a[1] = 2; a[2] = 3; p = a; return *p++; }'
+assert 3 'int main() { int a[3]; int *p; a[0] = 1; a[1] = 2; a[2] = 3; p = a + 1; return *++p; }'
+assert 2 'int main() { int a[3]; int *p; a[0] = 1; a[1] = 2; a[2] = 3; p = a + 2; p--; return *p; }'
+assert 3 'int main() { char *s; int n; s = "abc"; n = 0; while (*s++) n++; return n; }'
+assert 127 'int main() { char c; c = 127; return c++; }'
+assert 128 'int main() { char c; c = 127; c++; return c + 256; }'
+assert 4 'int main() { int a[3]; int i; a[0] = 0; a[1] = 0; a[2] = 0; i = 0; a[i++] += 4; return a[0] + i * 0 + a[1]; }'
+assert 1 'int main() { int a[3]; int i; a[0] = 0; a[1] = 0; a[2] = 0; i = 0; a[i++] += 4; return i; }'
+assert 45 'int main() { int i; int sum; sum = 0; for (i = 0; i < 10; i++) sum += i; return sum; }'
+assert 12 'struct s { int x; char y; }; int main() { struct s v; struct s *p; p = &v; p->x = 10; p->x += 2; v.y = 3; v.y--; return p->x; }'
+assert 5 'int main() { int a; int *p; a = 3; p = &a; *p += 2; return a; }'
+
+# conditional and comma operators
+assert 2 'int main() { return 1 ? 2 : 3; }'
+assert 3 'int main() { return 0 ? 2 : 3; }'
+assert 5 'int main() { int a; a = 4; return a > 3 ? a + 1 : a - 1; }'
+assert 3 'int main() { int a; a = 2; return a > 3 ? a + 1 : a - 1 + 2; }'
+assert 7 'int main() { return 0 ? 1 : 0 ? 2 : 7; }'
+assert 2 'int main() { return 1 ? 0 ? 3 : 2 : 1; }'
+assert 10 'int main() { int a; int b; a = 0; b = 0; 1 ? (a = 10) : (b = 20); return a + b; }'
+assert 20 'int main() { int a; int b; a = 0; b = 0; 0 ? (a = 10) : (b = 20); return a + b; }'
+assert 4 'int main() { int a; a = 1; a = a ? 4 : 5; return a; }'
+assert 2 'int main() { int a[2]; int *p; a[0] = 1; a[1] = 2; p = 1 ? a + 1 : 0; return *p; }'
+assert 1 'int main() { int a[2]; int *p; a[0] = 1; a[1] = 2; p = 0 ? 0 : a; return *p; }'
+assert 8 'int main() { int x; return sizeof(1 ? &x : 0); }'
+assert 4 'int main() { char c; return sizeof(1 ? c : c); }'
+assert 98 'int main() { char *s; s = "abc"; return *(0 ? s : s + 1); }'
+assert 3 'int main() { int a[1 ? 3 : 5]; return sizeof(a) / 4; }'
+assert 3 'int main() { return (1, 2, 3); }'
+assert 5 'int main() { int a; int b; a = (b = 2, b + 3); return a; }'
+assert 55 'int main() { int i; int j; int sum; sum = 0; for (i = 0, j = 10; i < j; i++, j--) sum += i + j; return sum + 5; }'
+assert 6 'int main() { int a; a = 1; a++, a++, a *= 2; return a; }'
+assert 15 'int sub3(int a, int b, int c) { return a - b - c; } int main() { int x; return sub3((x = 20, x), 2, 3); }'
+assert 4 'int main() { int a; a = (1, 2) ? 4 : 5; return a; }'
+
+# break and continue
+assert 3 'int main() { int i; i = 0; while (1) { if (i == 3) break; i++; } return i; }'
+assert 5 'int main() { int i; for (i = 0; ; i++) { if (i == 5) break; } return i; }'
+assert 25 'int main() { int i; int sum; sum = 0; for (i = 0; i < 10; i++) { if (i % 2 == 0) continue; sum += i; } return sum; }'
+assert 25 'int main() { int i; int sum; sum = 0; i = 0; while (i < 10) { i++; if (i % 2 == 0) continue; sum += i; } return sum; }'
+assert 10 'int main() { int i; int j; int n; n = 0; for (i = 0; i < 5; i++) { for (j = 0; j < 10; j++) { if (j == 2) break; n++; } } return n; }'
+assert 20 'int main() { int i; int j; int n; n = 0; for (i = 0; i < 5; i++) { for (j = 0; j < 10; j++) { if (j >= 4) continue; n++; } if (i > 10) break; } return n; }'
+assert 4 'int main() { int i; i = 0; for (;;) { i++; if (i < 4) continue; break; } return i; }'
+
+# do-while
+assert 5 'int main() { int i; i = 0; do i++; while (i < 5); return i; }'
+assert 1 'int main() { int i; i = 0; do { i++; } while (0); return i; }'
+assert 10 'int main() { int i; i = 10; do { if (i == 10) break; i++; } while (1); return i; }'
+assert 25 'int main() { int i; int sum; sum = 0; i = 0; do { i++; if (i % 2 == 0) continue; sum += i; } while (i < 10); return sum; }'
+assert 3 'int main() { int i; i = 0; do { i++; if (i > 2) continue; } while (i < 3); return i; }'
+assert 12 'int main() { int i; int j; int n; n = 0; i = 0; do { j = 0; do { n++; } while (++j < 4); } while (++i < 3); return n; }'
+
+# switch
+assert 5 'int main() { int x; x = 2; switch (x) { case 1: return 4; case 2: return 5; case 3: return 6; } return 7; }'
+assert 7 'int main() { int x; x = 9; switch (x) { case 1: return 4; case 2: return 5; case 3: return 6; } return 7; }'
+assert 8 'int main() { int x; x = 9; switch (x) { case 1: return 4; default: return 8; case 2: return 5; } return 7; }'
+assert 11 'int main() { int x; int n; x = 1; n = 0; switch (x) { case 1: n += 1; case 2: n += 10; break; case 3: n += 100; } return n; }'
+assert 111 'int main() { int x; int n; x = 0; n = 0; switch (x) { default: n += 100; case 1: n += 1; case 2: n += 10; } return n; }'
+assert 3 'int f(int x) { switch (x) { case 0: return 10; case 1: return 11; case 2: return 12; case 3: return 13; case 4: return 14; case 6: return 16; default: return 3; } } int main() { return f(5); }'
+assert 16 'int f(int x) { switch (x) { case 0: return 10; case 1: return 11; case 2: return 12; case 3: return 13; case 4: return 14; case 6: return 16; default: return 3; } } int main() { return f(6); }'
+assert 10 'int f(int x) { switch (x) { case 0: return 10; case 1: return 11; case 2: return 12; case 3: return 13; case 4: return 14; case 6: return 16; default: return 3; } } int main() { return f(0); }'
+assert 3 'int f(int x) { switch (x) { case 0: return 10; case 1: return 11; case 2: return 12; case 3: return 13; case 4: return 14; case 6: return 16; default: return 3; } } int main() { return f(-1) + f(7) - f(100); }'
+assert 2 'int f(int x) { switch (x) { case -2: return 1; case -1: return 2; case 0: return 3; case 1: return 4; } return 0; } int main() { return f(-1); }'
+assert 4 'int f(int x) { switch (x) { case 100: return 1; case 2000: return 2; case -5: return 3; case 30000: return 4; } return 0; } int main() { return f(30000); }'
+assert 0 'int f(int x) { switch (x) { case 100: return 1; case 2000: return 2; case -5: return 3; case 30000: return 4; } return 0; } int main() { return f(7); }'
+assert 24 'int main() { int i; int n; n = 0; for (i = 0; i < 10; i++) { switch (i % 3) { case 0: continue; case 1: n += 2; break; default: n += 4; } n += 1; } return n; }'
+assert 21 'int main() { int x; int y; x = 1; y = 2; switch (x) { case 1: switch (y) { case 1: return 11; case 2: return 21; } return 31; case 2: return 41; } return 0; }'
+assert 6 'enum { A, B, C }; int main() { int n; n = 0; switch (B) { case A: n = 1; break; case B: n = 6; break; case C: n = 9; } return n; }'
+assert 3 'int main() { switch (3) { case 1 + 2: return 3; case 1 << 2: return 4; } return 0; }'
+assert 2 'int main() { int x; x = 0; switch (1) { case 1: { int y; y = 2; x = y; } } return x; }'
+assert 9 'int main() { switch (5) { } return 9; }'
+
 echo OK
\ No newline at end of file